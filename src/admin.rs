@@ -0,0 +1,71 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a fetched admin code is kept, shorter than the minute after which ceramic expires
+/// admin codes
+const ADMIN_CODE_TTL: Duration = Duration::from_secs(50);
+
+/// Admin code fetched ahead for the next admin request of a batch, shared by a client and its
+/// clones. Codes are only fetched ahead while more requests of a batch follow, since a code that
+/// is not used in time is wasted. Ceramic discards admin codes once used, so each code is taken
+/// by a single request.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct AdminSession {
+    code: Arc<Mutex<Option<(String, Instant)>>>,
+}
+
+impl AdminSession {
+    /// Take the code fetched ahead, if it was fetched recently enough to still be valid, so that
+    /// no other request sends it
+    pub(crate) fn take(&self) -> Option<String> {
+        let mut code = self.code.lock().expect("admin session poisoned");
+        code.take()
+            .filter(|(_, fetched)| fetched.elapsed() < ADMIN_CODE_TTL)
+            .map(|(code, _)| code)
+    }
+
+    /// Keep a freshly fetched admin code for the next admin request
+    pub(crate) fn set(&self, code: String) {
+        *self.code.lock().expect("admin session poisoned") = Some((code, Instant::now()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_take_code_once() {
+        let session = AdminSession::default();
+        assert_eq!(session.take(), None);
+        session.set("first".to_string());
+        let shared = session.clone();
+        assert_eq!(shared.take().as_deref(), Some("first"));
+        assert_eq!(session.take(), None);
+        session.set("second".to_string());
+        assert_eq!(shared.take().as_deref(), Some("second"));
+        assert_eq!(shared.take(), None);
+    }
+
+    #[cfg(feature = "mock")]
+    mod mock {
+        use crate::mock::tests::setup;
+        use crate::ModelAccountRelation;
+
+        #[tokio::test]
+        async fn should_use_admin_codes_once() {
+            let (mock, ceramic, model) = setup(ModelAccountRelation::List).await;
+            ceramic.index_model(&model).await.unwrap();
+            ceramic.list_indexed_models().await.unwrap();
+            ceramic.node_status().await.unwrap();
+            // Single requests fetch one code each, without fetching ahead
+            assert_eq!(mock.admin_code_requests(), 3);
+
+            // A rejected code is replaced once
+            mock.expire_next_admin_code();
+            let models = ceramic.list_indexed_models().await.unwrap();
+            assert_eq!(models.models.len(), 1);
+            assert_eq!(mock.admin_code_requests(), 5);
+        }
+    }
+}
@@ -1,13 +1,16 @@
 use crate::query::FilterQuery;
+use crate::{CommitId, Error, Result};
 use ceramic_event::{
-    Base64String, Base64UrlString, Jws, MultiBase32String, MultiBase36String, StreamId,
-    StreamIdType,
+    Base64String, Base64UrlString, Cid, Jws, MultiBase36String, StreamId, StreamIdType,
 };
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Header for block data
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BlockHeader {
     /// Family that block belongs to
@@ -19,7 +22,7 @@ pub struct BlockHeader {
 }
 
 /// Block data
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BlockData<T: Serialize> {
     /// Header for block
@@ -33,13 +36,25 @@ pub struct BlockData<T: Serialize> {
     /// IPFS Linked Block
     #[serde(skip_serializing_if = "Option::is_none")]
     pub linked_block: Option<Base64String>,
-    /// Related cacao block
+    /// Related cacao block, when the block is signed by a did session
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cacao_block: Option<Base64String>,
+}
+
+/// Options applied by the node when writing a commit
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteOpts {
+    /// Request an anchor of the stream after the commit is applied
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<bool>,
+    /// Publish the commit to the network
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub cacao_block: Option<MultiBase32String>,
+    pub publish: Option<bool>,
 }
 
 /// Create request for http api
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateRequest<T: Serialize> {
     /// Type of stream to create
@@ -48,10 +63,13 @@ pub struct CreateRequest<T: Serialize> {
     /// Data to use when creating stream
     #[serde(rename = "genesis")]
     pub block: BlockData<T>,
+    /// Options for writing the genesis commit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opts: Option<WriteOpts>,
 }
 
 /// Update request for http api
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateRequest {
     /// Type of stream to update
@@ -62,40 +80,225 @@ pub struct UpdateRequest {
     pub block: BlockData<Base64String>,
     /// Stream to update
     pub stream_id: MultiBase36String,
+    /// Options for writing the commit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opts: Option<WriteOpts>,
+}
+
+/// Kind of commit in the log of a stream
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "u8", into = "u8")]
+pub enum LogEntryType {
+    /// Genesis commit that created the stream
+    Genesis,
+    /// Signed commit that updated the stream
+    Signed,
+    /// Anchor commit that timestamped the stream
+    Anchor,
+    /// Kind of commit not known to this crate, with its raw value
+    Unknown(u8),
+}
+
+impl From<u8> for LogEntryType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Genesis,
+            1 => Self::Signed,
+            2 => Self::Anchor,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+impl From<LogEntryType> for u8 {
+    fn from(value: LogEntryType) -> Self {
+        match value {
+            LogEntryType::Genesis => 0,
+            LogEntryType::Signed => 1,
+            LogEntryType::Anchor => 2,
+            LogEntryType::Unknown(other) => other,
+        }
+    }
 }
 
 /// Log entry for stream
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StateLog {
     /// CID for stream
     pub cid: MultiBase36String,
+    /// Kind of commit
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<LogEntryType>,
+    /// Time in seconds since the epoch the commit was anchored at
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    /// Time in seconds since the epoch the signature of the commit expires at
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiration_time: Option<u64>,
 }
 
 /// Metadata for stream
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Metadata {
     /// Controllers for stream
     pub controllers: Vec<String>,
     /// Model for stream
     pub model: StreamId,
+    /// Unique value of the stream, distinguishing streams with otherwise identical genesis
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unique: Option<Value>,
+    /// Stream the document belongs to, such as a parent document or project
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    /// Whether the document should be indexed by the node
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub should_index: Option<bool>,
+    /// Fields not modeled by this crate
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// Signature status of a stream
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "u8", into = "u8")]
+pub enum SignatureStatus {
+    /// Only the unsigned genesis commit is present
+    Genesis,
+    /// Some commits are signed
+    Partial,
+    /// All commits are signed
+    Signed,
+    /// Signature status not known to this crate, with its raw value
+    Unknown(u8),
+}
+
+impl From<u8> for SignatureStatus {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Genesis,
+            1 => Self::Partial,
+            2 => Self::Signed,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+impl From<SignatureStatus> for u8 {
+    fn from(value: SignatureStatus) -> Self {
+        match value {
+            SignatureStatus::Genesis => 0,
+            SignatureStatus::Partial => 1,
+            SignatureStatus::Signed => 2,
+            SignatureStatus::Unknown(other) => other,
+        }
+    }
+}
+
+/// Pending state of a stream, applied once its latest commit is anchored
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NextState {
+    /// Pending content of the stream
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<Value>,
+    /// Pending metadata of the stream
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    /// Fields not modeled by this crate
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// Anchoring progress of a stream
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum StreamAnchorStatus {
+    /// No anchor was requested for the latest commit
+    NotRequested,
+    /// An anchor was requested and is waiting to be processed
+    Pending,
+    /// The anchor service is processing the request
+    Processing,
+    /// The latest commit was anchored
+    Anchored,
+    /// The anchor service failed to anchor the latest commit
+    Failed,
+    /// The anchor request was replaced by a request for a later commit
+    Replaced,
+    /// Anchoring progress not known to this crate
+    #[serde(other)]
+    Unknown,
+}
+
+impl StreamAnchorStatus {
+    /// Whether anchoring has finished, successfully or not
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Anchored | Self::Failed)
+    }
+}
+
+/// Proof that a stream was anchored on chain
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnchorProof {
+    /// Chain the anchor transaction was written to
+    pub chain_id: String,
+    /// CID of the anchor transaction
+    pub tx_hash: String,
+    /// CID of the root of the merkle tree that was anchored
+    pub root: String,
+    /// Type of the anchor transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_type: Option<String>,
 }
 
 /// Current state of stream
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StreamState {
+    /// Numeric type of the stream
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<u64>,
+    /// Name of the stream type, returned by older nodes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doctype: Option<String>,
     /// Content of stream
     pub content: Value,
+    /// Pending state of the stream
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<NextState>,
     /// Log of stream
     pub log: Vec<StateLog>,
     /// Metadata for stream
     pub metadata: Metadata,
+    /// Signature status of the stream
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<SignatureStatus>,
+    /// Anchoring progress of the latest commit, if reported by the node
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchor_status: Option<StreamAnchorStatus>,
+    /// Proof of the latest anchor of the stream
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchor_proof: Option<AnchorProof>,
+    /// Time the pending anchor is scheduled for, as an ISO 8601 timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchor_scheduled_for: Option<String>,
+    /// Fields not modeled by this crate
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl StreamState {
+    /// Log entries of the given kind
+    pub fn log_entries(&self, r#type: LogEntryType) -> impl Iterator<Item = &StateLog> {
+        self.log.iter().filter(move |l| l.r#type == Some(r#type))
+    }
 }
 
 /// Response from request against streams endpoint
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StreamsResponse {
     /// ID of stream requested
@@ -104,6 +307,23 @@ pub struct StreamsResponse {
     pub state: Option<StreamState>,
 }
 
+impl StreamsResponse {
+    /// Anchoring progress of the latest commit, if reported by the node
+    pub fn anchor_status(&self) -> Option<StreamAnchorStatus> {
+        self.state.as_ref().and_then(|s| s.anchor_status)
+    }
+
+    /// CID of the latest commit of the stream
+    pub fn tip(&self) -> Result<Cid> {
+        let tip = self
+            .state
+            .as_ref()
+            .and_then(|s| s.log.last())
+            .ok_or_else(|| Error::NoCommits(self.stream_id.clone()))?;
+        Cid::from_str(tip.cid.as_ref()).map_err(|e| Error::Encoding(e.into()))
+    }
+}
+
 /// Response from request against streams endpoint or error
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
@@ -114,41 +334,62 @@ pub enum StreamsResponseOrError {
         error: String,
     },
     /// Response was ok
-    Ok(StreamsResponse),
+    Ok(Box<StreamsResponse>),
 }
 
 impl StreamsResponseOrError {
     /// Resolve or throw error from response
-    pub fn resolve(self, context: &str) -> anyhow::Result<StreamsResponse> {
+    pub fn resolve(self, context: &str) -> Result<StreamsResponse> {
         match self {
-            Self::Error { error } => {
-                anyhow::bail!(format!("{}: {}", context, error))
-            }
-            Self::Ok(resp) => Ok(resp),
+            Self::Error { error } => Err(Error::Ceramic {
+                context: context.to_string(),
+                message: error,
+            }),
+            Self::Ok(resp) => Ok(*resp),
         }
     }
 }
 
-/// Json wrapper around jws
+/// Error body returned by ceramic
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct ErrorResponse {
+    /// Error message
+    pub error: String,
+}
+
+/// Json wrapper around jws
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct JwsValue {
     /// Jws for a specific commit
     pub jws: Jws,
+    /// Block the jws payload links to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linked_block: Option<Base64String>,
 }
 
 /// Commit for a specific stream
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Commit {
     /// Commit id
     pub cid: MultiBase36String,
-    /// Value of commit
+    /// Value of commit, if it is a signed commit
+    #[serde(default, deserialize_with = "signed_value")]
     pub value: Option<JwsValue>,
 }
 
+/// Deserialize the value of a signed commit, ignoring unsigned genesis and anchor commits
+fn signed_value<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<JwsValue>, D::Error> {
+    let value = Option::<Value>::deserialize(deserializer)?;
+    Ok(value.and_then(|v| serde_json::from_value(v).ok()))
+}
+
 /// Response from commits endpoint
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CommitsResponse {
     /// ID of stream for commit
@@ -175,12 +416,28 @@ pub struct IndexModelData {
     pub models: Vec<ModelData>,
 }
 
+/// Outcome of indexing or unindexing a model of a batch
+#[derive(Debug)]
+pub struct ModelIndexOutcome {
+    /// Model that was indexed or unindexed
+    pub model: StreamId,
+    /// Whether the node accepted the model
+    pub result: Result<()>,
+}
+
+impl ModelIndexOutcome {
+    /// Whether the node accepted the model
+    pub fn is_ok(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
 /// Request to list indexed models
 #[derive(Serialize)]
 pub struct ListIndexedModelsRequest {}
 
 /// Response list of indexed models
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ListIndexedModelsResponse {
     /// List of indexed models
@@ -222,7 +479,7 @@ impl AdminApiRequest {
 }
 
 impl TryFrom<Jws> for AdminApiRequest {
-    type Error = anyhow::Error;
+    type Error = Error;
     fn try_from(value: Jws) -> Result<Self, Self::Error> {
         let maybe_sig = value
             .signatures
@@ -232,13 +489,15 @@ impl TryFrom<Jws> for AdminApiRequest {
             let sig = format!("{}.{}.{}", protected, value.payload, sig);
             Ok(Self { jws: sig })
         } else {
-            anyhow::bail!("Invalid jws, no signatures")
+            Err(Error::Signing(anyhow::anyhow!(
+                "Invalid jws, no signatures"
+            )))
         }
     }
 }
 
 /// Pagination for query
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(untagged, rename_all = "camelCase")]
 pub enum Pagination {
     /// Paginate forward
@@ -268,8 +527,29 @@ impl Default for Pagination {
     }
 }
 
+impl Pagination {
+    /// Pagination for the page following this one in the same direction, if there is one
+    pub fn next_page(&self, page_info: &PageInfo) -> Option<Self> {
+        match self {
+            Self::First { first, .. } if page_info.has_next_page => {
+                page_info.end_cursor.as_ref().map(|cursor| Self::First {
+                    first: *first,
+                    after: Some(cursor.clone()),
+                })
+            }
+            Self::Last { last, .. } if page_info.has_previous_page => {
+                page_info.start_cursor.as_ref().map(|cursor| Self::Last {
+                    last: *last,
+                    before: Some(cursor.clone()),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
 /// Request to query
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct QueryRequest {
     /// Model to query documents for
@@ -285,7 +565,7 @@ pub struct QueryRequest {
 }
 
 /// Node returned from query
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct QueryNode {
     /// Content of node
@@ -295,7 +575,7 @@ pub struct QueryNode {
 }
 
 /// Edge returned from query
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct QueryEdge {
     /// Cursor for edge
@@ -305,21 +585,21 @@ pub struct QueryEdge {
 }
 
 /// Info about query pages
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PageInfo {
     /// Whether next page exists
     pub has_next_page: bool,
     /// Whether previous page exists
     pub has_previous_page: bool,
-    /// Cursor for next page
-    pub end_cursor: Base64UrlString,
-    /// Cursor for previous page
-    pub start_cursor: Base64UrlString,
+    /// Cursor for next page, absent when the page is empty
+    pub end_cursor: Option<Base64UrlString>,
+    /// Cursor for previous page, absent when the page is empty
+    pub start_cursor: Option<Base64UrlString>,
 }
 
 /// Response to query
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct QueryResponse {
     /// Edges of query
@@ -348,6 +628,134 @@ pub struct TypedQueryResponse<T> {
     pub page_info: PageInfo,
 }
 
+/// Request to pin a stream, or to unpin it
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PinRequest {
+    /// Pin the stream again, fetching its content, even if it is already pinned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub force: Option<bool>,
+}
+
+/// Request to list pinned streams
+#[derive(Serialize)]
+pub struct ListPinsRequest {}
+
+/// Response from pinning or unpinning a stream
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PinResponse {
+    /// Stream that was pinned or unpinned
+    pub stream_id: StreamId,
+    /// Whether the stream is now pinned
+    pub is_pinned: bool,
+}
+
+/// Response listing pinned streams
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListPinsResponse {
+    /// Ids of the pinned streams
+    pub pinned_stream_ids: Vec<StreamId>,
+}
+
+impl ListPinsResponse {
+    /// Iterate over the ids of the pinned streams
+    pub fn iter(&self) -> std::slice::Iter<'_, StreamId> {
+        self.pinned_stream_ids.iter()
+    }
+}
+
+impl IntoIterator for ListPinsResponse {
+    type Item = StreamId;
+    type IntoIter = std::vec::IntoIter<StreamId>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pinned_stream_ids.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a ListPinsResponse {
+    type Item = &'a StreamId;
+    type IntoIter = std::slice::Iter<'a, StreamId>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Options for loading a stream in a multiquery
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadOpts {
+    /// Load the stream as it was anchored at this unix timestamp, in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub at_time: Option<u64>,
+}
+
+/// Stream to load in a multiquery
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiQuery {
+    /// Stream or commit to load, as multibase
+    pub stream_id: String,
+    /// Paths of links in the content of the stream to load along with it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Options for loading the stream
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opts: Option<LoadOpts>,
+}
+
+impl MultiQuery {
+    /// Load the latest state of a stream
+    pub fn new(stream_id: &StreamId) -> Self {
+        Self {
+            stream_id: stream_id.to_string(),
+            paths: Vec::new(),
+            opts: None,
+        }
+    }
+
+    /// Load a stream as it was at a commit
+    pub fn at_commit(commit_id: &CommitId) -> Result<Self> {
+        Ok(Self {
+            stream_id: commit_id.to_multibase()?,
+            paths: Vec::new(),
+            opts: None,
+        })
+    }
+
+    /// Also load the stream linked from the content at `path`, e.g. `/author`
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    /// Load the stream as it was anchored at the given time
+    pub fn at_time(mut self, at_time: SystemTime) -> Result<Self> {
+        let at_time = at_time
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| Error::Encoding(e.into()))?;
+        self.opts = Some(LoadOpts {
+            at_time: Some(at_time.as_secs()),
+        });
+        Ok(self)
+    }
+}
+
+/// Request to load many streams at once
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiQueryRequest {
+    /// Streams to load
+    pub queries: Vec<MultiQuery>,
+}
+
+/// States of the streams loaded by a multiquery, by the stream or commit id of their query and
+/// the ids of streams loaded through paths. Streams the node could not load are left out.
+pub type MultiQueryResponse = HashMap<String, StreamState>;
+
 /// Healthcheck request for http api
 #[derive(Serialize)]
 pub struct HealthcheckRequest {}
@@ -357,7 +765,7 @@ pub struct HealthcheckRequest {}
 pub struct NodeStatusRequest {}
 
 /// Node status response for http api
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NodeStatusResponse {
     /// A random UUID that is generated each time a node starts up.
@@ -376,7 +784,7 @@ pub struct NodeStatusResponse {
 }
 
 /// Information about the anchoring service.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AnchorStatus {
     /// The URL of the Ceramic Anchor Service used to request anchors.
@@ -389,7 +797,7 @@ pub struct AnchorStatus {
 }
 
 /// Information about the connected IPFS node.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct IpfsStatus {
     /// PeerId of the connected ipfs node
@@ -399,7 +807,7 @@ pub struct IpfsStatus {
 }
 
 /// Status about the ComposeDB specific operations of the node.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ComposeDBStatus {
     /// The list of models Ids that are being indexed.
@@ -409,7 +817,7 @@ pub struct ComposeDBStatus {
 }
 
 /// Status of all sync operations.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SyncStatus {
     /// Status of currently active sync operations.
@@ -421,7 +829,7 @@ pub struct SyncStatus {
 }
 
 /// Status of currently active sync operations.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ActiveSyncStatus {
     /// The block the sync starts at
@@ -439,7 +847,7 @@ pub struct ActiveSyncStatus {
 }
 
 /// Status of continuously running sync operations.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ContinuousSyncStatus {
     /// The first block recevied form the chain on node startup
@@ -454,7 +862,7 @@ pub struct ContinuousSyncStatus {
     pub models: Vec<StreamId>,
 }
 /// Status of pending sync operations.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PendingSyncStatus {
     /// The block the sync starts at
@@ -497,4 +905,223 @@ mod tests {
             r#"{"model":"kjzl6hvfrbw6c8apa5yce6ah3fsz9sgrh6upniy0tz8z76gdm169ds3tf8c051t","account":"test","queryFilters":{"where":{"id":{"equalTo":"1"}}},"first":100}"#
         );
     }
+
+    #[test]
+    fn should_page_forward_and_backward() {
+        let page_info: PageInfo = serde_json::from_value(serde_json::json!({
+            "hasNextPage": true,
+            "hasPreviousPage": false,
+            "startCursor": "start",
+            "endCursor": "end",
+        }))
+        .unwrap();
+        let next = Pagination::First {
+            first: 10,
+            after: None,
+        }
+        .next_page(&page_info);
+        assert_eq!(
+            serde_json::to_value(next).unwrap(),
+            serde_json::json!({"first": 10, "after": "end"})
+        );
+        let previous = Pagination::Last {
+            last: 10,
+            before: None,
+        }
+        .next_page(&page_info);
+        assert!(previous.is_none());
+    }
+
+    #[test]
+    fn should_stop_paging_on_empty_page() {
+        let page_info: PageInfo = serde_json::from_value(serde_json::json!({
+            "hasNextPage": true,
+            "hasPreviousPage": true,
+            "startCursor": null,
+            "endCursor": null,
+        }))
+        .unwrap();
+        assert!(Pagination::default().next_page(&page_info).is_none());
+    }
+
+    fn round_trip<T: Serialize + serde::de::DeserializeOwned>(value: &T) {
+        let json = serde_json::to_value(value).unwrap();
+        let parsed: T = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(parsed).unwrap(), json);
+    }
+
+    fn block_data() -> BlockData<Value> {
+        let jws: Jws = serde_json::from_value(serde_json::json!({
+            "payload": "AXESIA",
+            "signatures": [{"protected": "eyJhbGciOiJFZERTQSJ9", "signature": "c2ln"}],
+        }))
+        .unwrap();
+        BlockData {
+            header: BlockHeader {
+                family: "test".to_string(),
+                controllers: vec!["did:key:z6Mk".to_string()],
+                model: serde_json::from_value(serde_json::json!("zgEEAXFxCwAJaWRtb2RlbA")).unwrap(),
+            },
+            data: Some(serde_json::json!({"red": 5})),
+            jws: Some(jws),
+            linked_block: serde_json::from_value(serde_json::json!("omRkYXRh")).unwrap(),
+            cacao_block: None,
+        }
+    }
+
+    #[test]
+    fn should_decode_anchor_status() {
+        let state: StreamState = serde_json::from_value(serde_json::json!({
+            "content": {},
+            "log": [],
+            "metadata": {
+                "controllers": [],
+                "model": "kjzl6hvfrbw6c8apa5yce6ah3fsz9sgrh6upniy0tz8z76gdm169ds3tf8c051t",
+            },
+            "anchorStatus": "PENDING",
+            "anchorScheduledFor": "2023-11-14T22:13:20.000Z",
+        }))
+        .unwrap();
+        assert_eq!(state.anchor_status, Some(StreamAnchorStatus::Pending));
+        assert_eq!(
+            state.anchor_scheduled_for.as_deref(),
+            Some("2023-11-14T22:13:20.000Z")
+        );
+        assert!(!StreamAnchorStatus::Pending.is_final());
+        assert!(StreamAnchorStatus::Failed.is_final());
+        assert!(state.anchor_proof.is_none());
+    }
+
+    #[test]
+    fn should_decode_unknown_statuses() {
+        let state: StreamState = serde_json::from_value(serde_json::json!({
+            "content": {},
+            "log": [{"cid": "bagcqcera", "type": 7}],
+            "metadata": {
+                "controllers": [],
+                "model": "kjzl6hvfrbw6c8apa5yce6ah3fsz9sgrh6upniy0tz8z76gdm169ds3tf8c051t",
+            },
+            "signature": 9,
+            "anchorStatus": "QUEUED",
+        }))
+        .unwrap();
+        assert_eq!(state.log[0].r#type, Some(LogEntryType::Unknown(7)));
+        assert_eq!(state.signature, Some(SignatureStatus::Unknown(9)));
+        assert_eq!(state.anchor_status, Some(StreamAnchorStatus::Unknown));
+        assert!(!StreamAnchorStatus::Unknown.is_final());
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["log"][0]["type"], 7);
+        assert_eq!(json["signature"], 9);
+    }
+
+    #[test]
+    fn should_decode_full_state() {
+        let json = serde_json::json!({
+            "type": 3,
+            "content": {"red": 1},
+            "next": {"content": {"red": 2}},
+            "log": [
+                {"cid": "bagcqcera", "type": 0},
+                {"cid": "bagcqcerb", "type": 1, "expirationTime": 1700000000u64},
+                {"cid": "bafyreic", "type": 2, "timestamp": 1690000000u64},
+            ],
+            "metadata": {
+                "controllers": ["did:key:z6Mk"],
+                "model": "kjzl6hvfrbw6c8apa5yce6ah3fsz9sgrh6upniy0tz8z76gdm169ds3tf8c051t",
+                "shouldIndex": true,
+                "tags": ["a"],
+            },
+            "signature": 2,
+            "anchorStatus": "ANCHORED",
+            "custom": {"nested": true},
+        });
+        let state: StreamState = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(state.r#type, Some(3));
+        assert_eq!(state.signature, Some(SignatureStatus::Signed));
+        assert_eq!(state.metadata.should_index, Some(true));
+        assert_eq!(state.metadata.other["tags"], serde_json::json!(["a"]));
+        assert_eq!(state.other["custom"], serde_json::json!({"nested": true}));
+        let anchors: Vec<_> = state.log_entries(LogEntryType::Anchor).collect();
+        assert_eq!(anchors.len(), 1);
+        assert_eq!(anchors[0].timestamp, Some(1690000000));
+        assert_eq!(serde_json::to_value(&state).unwrap(), json);
+    }
+
+    #[test]
+    fn should_iterate_pins() {
+        let model = "kjzl6hvfrbw6c8apa5yce6ah3fsz9sgrh6upniy0tz8z76gdm169ds3tf8c051t";
+        let document = "kjzl6kcym7w8y7nzgytqayf6aro12zt0mm01n6ydjomyvvklcspx9kr6gpbwd09";
+        let pins: ListPinsResponse =
+            serde_json::from_value(serde_json::json!({"pinnedStreamIds": [model, document]}))
+                .unwrap();
+        let ids: Vec<_> = pins.iter().map(StreamId::to_string).collect();
+        assert_eq!(ids, vec![model, document]);
+        assert_eq!((&pins).into_iter().count(), 2);
+        let ids: Vec<_> = pins.into_iter().map(|id| id.to_string()).collect();
+        assert_eq!(ids, vec![model, document]);
+    }
+
+    #[test]
+    fn should_round_trip_requests() {
+        let stream_id = "kjzl6kcym7w8y7nzgytqayf6aro12zt0mm01n6ydjomyvvklcspx9kr6gpbwd09";
+        round_trip(&block_data());
+        round_trip(&block_data().header);
+        round_trip(&CreateRequest {
+            r#type: StreamIdType::ModelInstanceDocument,
+            block: block_data(),
+            opts: Some(WriteOpts {
+                anchor: Some(true),
+                publish: None,
+            }),
+        });
+        let data = block_data();
+        round_trip(&UpdateRequest {
+            r#type: StreamIdType::ModelInstanceDocument,
+            block: BlockData {
+                header: data.header,
+                data: None,
+                jws: data.jws,
+                linked_block: data.linked_block.clone(),
+                cacao_block: data.linked_block,
+            },
+            stream_id: serde_json::from_value(serde_json::json!(stream_id)).unwrap(),
+            opts: None,
+        });
+    }
+
+    #[test]
+    fn should_round_trip_query_request() {
+        let mut where_filter = HashMap::new();
+        where_filter.insert("red".to_string(), OperationFilter::GreaterThan(0.5.into()));
+        where_filter.insert(
+            "name".to_string(),
+            OperationFilter::In(vec!["ball".into(), 3.into()]),
+        );
+        let req = QueryRequest {
+            model: StreamId::from_str(
+                "kjzl6hvfrbw6c8apa5yce6ah3fsz9sgrh6upniy0tz8z76gdm169ds3tf8c051t",
+            )
+            .unwrap(),
+            account: "test".to_string(),
+            query: Some(FilterQuery::Not(Box::new(FilterQuery::Where(where_filter)))),
+            pagination: Pagination::Last {
+                last: 10,
+                before: serde_json::from_value(serde_json::json!("MQ")).unwrap(),
+            },
+        };
+        round_trip(&req);
+        round_trip(&Pagination::default());
+        let parsed: QueryRequest = serde_json::from_str(
+            r#"{"model":"kjzl6hvfrbw6c8apa5yce6ah3fsz9sgrh6upniy0tz8z76gdm169ds3tf8c051t","account":"test","first":5}"#,
+        )
+        .unwrap();
+        assert!(parsed.query.is_none());
+        assert!(matches!(
+            parsed.pagination,
+            Pagination::First {
+                first: 5,
+                after: None
+            }
+        ));
+    }
 }
@@ -0,0 +1,167 @@
+use crate::api;
+use ceramic_event::{Base64String, StreamId};
+use serde::Serialize;
+use std::fmt;
+
+/// Model of an apply plan and the changes needed to deploy it
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedModel {
+    /// Name of the model
+    pub name: String,
+    /// Id of the model, derived from its definition and the signer
+    pub stream_id: StreamId,
+    /// Whether the genesis of the model will be posted, creating the model unless it already
+    /// exists
+    pub create: bool,
+    /// Whether the model is not indexed yet and will be indexed
+    pub index: bool,
+    #[serde(skip)]
+    pub(crate) request: Option<api::CreateRequest<Base64String>>,
+}
+
+impl fmt::Debug for PlannedModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PlannedModel")
+            .field("name", &self.name)
+            .field("stream_id", &self.stream_id)
+            .field("create", &self.create)
+            .field("index", &self.index)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PlannedModel {
+    /// Whether the model is already deployed and indexed
+    pub fn is_unchanged(&self) -> bool {
+        !self.create && !self.index
+    }
+}
+
+/// Changes needed to deploy and index a set of model definitions on a node
+#[derive(Debug, Serialize)]
+pub struct ApplyPlan {
+    /// Models of the plan, in the order of their definitions
+    pub models: Vec<PlannedModel>,
+}
+
+impl ApplyPlan {
+    /// Whether all models are already deployed and indexed
+    pub fn is_unchanged(&self) -> bool {
+        self.models.iter().all(PlannedModel::is_unchanged)
+    }
+
+    /// Models that will be created
+    pub fn to_create(&self) -> impl Iterator<Item = &PlannedModel> {
+        self.models.iter().filter(|m| m.create)
+    }
+
+    /// Models that will be indexed
+    pub fn to_index(&self) -> impl Iterator<Item = &PlannedModel> {
+        self.models.iter().filter(|m| m.index)
+    }
+}
+
+impl fmt::Display for ApplyPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for model in &self.models {
+            let change = match (model.create, model.index) {
+                (true, _) => "+ create and index",
+                (false, true) => "~ index",
+                (false, false) => "  unchanged",
+            };
+            writeln!(f, "{} {} ({})", change, model.name, model.stream_id)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const MODEL: &str = "kjzl6hvfrbw6c8apa5yce6ah3fsz9sgrh6upniy0tz8z76gdm169ds3tf8c051t";
+
+    fn planned(name: &str, create: bool, index: bool) -> PlannedModel {
+        PlannedModel {
+            name: name.to_string(),
+            stream_id: StreamId::from_str(MODEL).unwrap(),
+            create,
+            index,
+            request: None,
+        }
+    }
+
+    #[test]
+    fn should_summarize_changes() {
+        let plan = ApplyPlan {
+            models: vec![
+                planned("Deployed", false, false),
+                planned("Unindexed", false, true),
+                planned("New", true, true),
+            ],
+        };
+        assert!(!plan.is_unchanged());
+        let names = |models: Vec<&PlannedModel>| -> Vec<String> {
+            models.iter().map(|m| m.name.clone()).collect()
+        };
+        assert_eq!(names(plan.to_create().collect()), vec!["New"]);
+        assert_eq!(names(plan.to_index().collect()), vec!["Unindexed", "New"]);
+        assert_eq!(
+            plan.to_string(),
+            format!(
+                "  unchanged Deployed ({0})\n~ index Unindexed ({0})\n+ create and index New ({0})\n",
+                MODEL
+            )
+        );
+        let plan = ApplyPlan {
+            models: vec![planned("Deployed", false, false)],
+        };
+        assert!(plan.is_unchanged());
+    }
+
+    #[cfg(feature = "mock")]
+    mod mock {
+        use crate::mock::tests::{setup, Ball};
+        use crate::remote::CeramicRemoteHttpClient;
+        use crate::session::tests::{cacao, signer, DID};
+        use crate::{DidSession, Error, ModelAccountRelation, ModelDefinition};
+
+        #[tokio::test]
+        async fn should_plan_and_apply_models() {
+            let (mock, ceramic, deployed_id) = setup(ModelAccountRelation::List).await;
+            ceramic.index_model(&deployed_id).await.unwrap();
+            let models = vec![
+                ModelDefinition::new::<Ball>("TestBall", ModelAccountRelation::List).unwrap(),
+                ModelDefinition::new::<Ball>("OtherBall", ModelAccountRelation::List).unwrap(),
+                ModelDefinition::new::<Ball>("NewBall", ModelAccountRelation::List).unwrap(),
+            ];
+            // created but not indexed, so its genesis is posted again
+            ceramic.create_model(&models[1]).await.unwrap();
+
+            let plan = ceramic.apply_models(&models, true).await.unwrap();
+            let changes: Vec<_> = plan.models.iter().map(|m| (m.create, m.index)).collect();
+            assert_eq!(changes, vec![(false, false), (true, true), (true, true)]);
+            assert_eq!(plan.models[0].stream_id, deployed_id);
+            assert_eq!(mock.stream_ids().len(), 2);
+            // planning does not load streams, which a node may not answer until a timeout
+            assert!(!mock
+                .requests()
+                .iter()
+                .any(|r| r.method == "GET" && r.path.starts_with("/api/v0/streams/")));
+
+            ceramic.apply_models(&models, false).await.unwrap();
+            assert_eq!(mock.stream_ids().len(), 3);
+            assert_eq!(mock.indexed_models().len(), 3);
+            assert!(ceramic.plan_models(&models).await.unwrap().is_unchanged());
+
+            let session = DidSession::new(signer().await, cacao(DID, "2100-01-01T00:00:00.000Z"));
+            let ceramic = CeramicRemoteHttpClient::new_with_session(session, mock.url());
+            assert!(matches!(
+                ceramic.plan_models(&models).await,
+                Err(Error::SessionUnsupported(_))
+            ));
+        }
+    }
+}
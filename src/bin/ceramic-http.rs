@@ -0,0 +1,359 @@
+//! Command line client for common operations against the Ceramic HTTP API
+//!
+//! Results are written to stdout as json. Arguments taking json accept either the json itself
+//! or `@path` to read it from a file.
+use ceramic_event::{Base64UrlString, DidDocument, JwkSigner, StreamId};
+use ceramic_http_client::api::{ModelIndexOutcome, Pagination};
+use ceramic_http_client::remote::CeramicRemoteHttpClient;
+use ceramic_http_client::schemars::schema::RootSchema;
+use ceramic_http_client::{FilterQuery, ModelAccountRelation, ModelDefinition};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::json;
+use std::path::PathBuf;
+use url::Url;
+
+#[derive(Parser)]
+#[command(name = "ceramic-http", about = "Client for the Ceramic HTTP API")]
+struct Cli {
+    /// Url of the ceramic node
+    #[arg(long, env = "CERAMIC_URL", default_value = "http://localhost:7007")]
+    url: Url,
+    /// DID used to sign requests
+    #[arg(long, env = "DID_DOCUMENT")]
+    did: String,
+    /// Hex encoded private key of the DID
+    #[arg(long, env = "DID_PRIVATE_KEY", hide_env_values = true)]
+    private_key: String,
+    /// Family written to the header of created and updated streams
+    #[arg(long)]
+    family: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a model from a json schema file
+    CreateModel {
+        /// Name of the model
+        #[arg(long)]
+        name: String,
+        /// Path to the json schema of the model
+        #[arg(long)]
+        schema: PathBuf,
+        /// Description of the model
+        #[arg(long)]
+        description: Option<String>,
+        /// Number of instances of the model an account can have
+        #[arg(long, value_enum, default_value_t = Relation::List)]
+        account_relation: Relation,
+        /// Field identifying instances of a model with a set relation, may be repeated
+        #[arg(long = "set-field", required_if_eq("account_relation", "set"))]
+        set_fields: Vec<String>,
+        /// Create the model as an interface
+        #[arg(long)]
+        interface: bool,
+        /// Interface implemented by the model, may be repeated
+        #[arg(long)]
+        implements: Vec<StreamId>,
+        /// Field that cannot be changed once set, may be repeated
+        #[arg(long)]
+        immutable: Vec<String>,
+    },
+    /// Get the definition of a model
+    GetModel {
+        /// Model to load
+        model: StreamId,
+    },
+    /// Index models on the node
+    IndexModel {
+        /// Models to index
+        #[arg(required = true)]
+        models: Vec<StreamId>,
+    },
+    /// Stop indexing models on the node
+    UnindexModel {
+        /// Models to stop indexing
+        #[arg(required = true)]
+        models: Vec<StreamId>,
+    },
+    /// List the models indexed on the node
+    ListIndexedModels,
+    /// Pin a stream on the node
+    Pin {
+        /// Stream to pin
+        stream_id: StreamId,
+        /// Pin the stream again even if it is already pinned
+        #[arg(long)]
+        force: bool,
+    },
+    /// Unpin a stream on the node
+    Unpin {
+        /// Stream to unpin
+        stream_id: StreamId,
+    },
+    /// List the streams pinned on the node
+    ListPins,
+    /// Create and index the models of a json array of model definitions that are not deployed
+    /// yet, printing the plan
+    Apply {
+        /// Model definitions, as json
+        models: String,
+        /// Only print the plan, without creating or indexing models
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Create a document, or set the content of the single or set document of a model
+    Create {
+        /// Model of the document
+        #[arg(long)]
+        model: StreamId,
+        /// Model only allows a single document per account
+        #[arg(long, conflicts_with = "unique")]
+        single: bool,
+        /// Value of a unique field of a model with a set relation, in the order of the model
+        /// fields, may be repeated
+        #[arg(long)]
+        unique: Vec<String>,
+        /// Content of the document
+        content: String,
+    },
+    /// Get the current state of a document
+    Get {
+        /// Document to load
+        stream_id: StreamId,
+    },
+    /// Load many documents in a single request
+    GetMany {
+        /// Documents to load
+        #[arg(required = true)]
+        stream_ids: Vec<StreamId>,
+    },
+    /// Update a document with a json patch
+    Update {
+        /// Model of the document
+        #[arg(long)]
+        model: StreamId,
+        /// Document to update
+        stream_id: StreamId,
+        /// Json patch to apply
+        patch: String,
+    },
+    /// Replace the content of a document
+    Replace {
+        /// Model of the document
+        #[arg(long)]
+        model: StreamId,
+        /// Document to replace
+        stream_id: StreamId,
+        /// New content of the document
+        content: String,
+    },
+    /// Query documents of a model
+    Query {
+        /// Model of the documents
+        #[arg(long)]
+        model: StreamId,
+        /// Filter documents must match
+        #[arg(long)]
+        filter: Option<String>,
+        /// Number of documents to return, paginating forward
+        #[arg(long, conflicts_with = "last")]
+        first: Option<u32>,
+        /// Cursor to paginate forward from
+        #[arg(long, conflicts_with = "last")]
+        after: Option<String>,
+        /// Number of documents to return, paginating backward
+        #[arg(long)]
+        last: Option<u32>,
+        /// Cursor to paginate backward from
+        #[arg(long, requires = "last")]
+        before: Option<String>,
+    },
+    /// Check that the node is alive
+    Healthcheck,
+    /// Get the status of the node
+    NodeStatus,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Relation {
+    List,
+    Single,
+    Set,
+}
+
+impl Relation {
+    fn with_fields(self, fields: Vec<String>) -> ModelAccountRelation {
+        match self {
+            Self::List => ModelAccountRelation::List,
+            Self::Single => ModelAccountRelation::Single,
+            Self::Set => ModelAccountRelation::Set { fields },
+        }
+    }
+}
+
+/// Parse a json argument, reading it from a file if it starts with `@`
+fn json_arg<T: DeserializeOwned>(arg: &str) -> anyhow::Result<T> {
+    let value = match arg.strip_prefix('@') {
+        Some(path) => serde_json::from_slice(&std::fs::read(path)?)?,
+        None => serde_json::from_str(arg)?,
+    };
+    Ok(value)
+}
+
+fn cursor(cursor: Option<String>) -> anyhow::Result<Option<Base64UrlString>> {
+    Ok(cursor
+        .map(|c| serde_json::from_value(json!(c)))
+        .transpose()?)
+}
+
+fn print<T: Serialize>(value: &T) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn print_outcomes(outcomes: Vec<ModelIndexOutcome>) -> anyhow::Result<()> {
+    let outcomes: Vec<_> = outcomes
+        .into_iter()
+        .map(|o| match o.result {
+            Ok(()) => json!({ "streamId": o.model }),
+            Err(e) => json!({ "streamId": o.model, "error": e.to_string() }),
+        })
+        .collect();
+    print(&outcomes)
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> anyhow::Result<()> {
+    let args = Cli::parse();
+    let signer = JwkSigner::new(DidDocument::new(&args.did), &args.private_key).await?;
+    let mut ceramic = CeramicRemoteHttpClient::new(signer, args.url);
+    if let Some(family) = args.family {
+        ceramic = ceramic.with_family(family);
+    }
+    match args.command {
+        Command::CreateModel {
+            name,
+            schema,
+            description,
+            account_relation,
+            set_fields,
+            interface,
+            implements,
+            immutable,
+        } => {
+            let schema: RootSchema = serde_json::from_slice(&std::fs::read(schema)?)?;
+            let mut model = ModelDefinition::from_schema(
+                &name,
+                account_relation.with_fields(set_fields),
+                &schema,
+            )?;
+            if let Some(description) = description {
+                model.with_description(description);
+            }
+            if interface {
+                model.as_interface();
+            }
+            for interface in implements {
+                model.with_implements(interface);
+            }
+            for field in immutable {
+                model.with_immutable_field(field);
+            }
+            let stream_id = ceramic.create_model(&model).await?;
+            print(&json!({ "streamId": stream_id }))
+        }
+        Command::GetModel { model } => print(&ceramic.get_model(&model).await?),
+        Command::IndexModel { models } => print_outcomes(ceramic.index_models(&models).await?),
+        Command::UnindexModel { models } => print_outcomes(ceramic.unindex_models(&models).await?),
+        Command::ListIndexedModels => print(&ceramic.list_indexed_models().await?),
+        Command::Pin { stream_id, force } => print(&ceramic.pin(&stream_id, force).await?),
+        Command::Unpin { stream_id } => print(&ceramic.unpin(&stream_id).await?),
+        Command::ListPins => print(&ceramic.list_pins().await?),
+        Command::Apply { models, dry_run } => {
+            let models: Vec<ModelDefinition> = json_arg(&models)?;
+            print(&ceramic.apply_models(&models, dry_run).await?)
+        }
+        Command::Create {
+            model,
+            single,
+            unique,
+            content,
+        } => {
+            let content: serde_json::Value = json_arg(&content)?;
+            let stream_id = if !unique.is_empty() {
+                ceramic
+                    .get_or_create_set_instance(&model, &unique, content)
+                    .await?
+                    .stream_id
+            } else if single {
+                ceramic
+                    .get_or_create_single_instance(&model, content)
+                    .await?
+                    .stream_id
+            } else {
+                ceramic.create_list_instance(&model, content).await?
+            };
+            print(&json!({ "streamId": stream_id }))
+        }
+        Command::Get { stream_id } => print(&ceramic.get(&stream_id).await?),
+        Command::GetMany { stream_ids } => {
+            let resps: serde_json::Map<_, _> = ceramic
+                .get_many(&stream_ids)
+                .await?
+                .into_iter()
+                .map(|(stream_id, resp)| {
+                    let resp = match resp {
+                        Ok(resp) => json!(resp.state),
+                        Err(e) => json!({ "error": e.to_string() }),
+                    };
+                    (stream_id.to_string(), resp)
+                })
+                .collect();
+            print(&resps)
+        }
+        Command::Update {
+            model,
+            stream_id,
+            patch,
+        } => print(
+            &ceramic
+                .update(&model, &stream_id, json_arg(&patch)?)
+                .await?,
+        ),
+        Command::Replace {
+            model,
+            stream_id,
+            content,
+        } => {
+            let content: serde_json::Value = json_arg(&content)?;
+            print(&ceramic.replace(&model, &stream_id, content).await?)
+        }
+        Command::Query {
+            model,
+            filter,
+            first,
+            after,
+            last,
+            before,
+        } => {
+            let filter: Option<FilterQuery> = filter.as_deref().map(json_arg).transpose()?;
+            let pagination = match last {
+                Some(last) => Pagination::Last {
+                    last,
+                    before: cursor(before)?,
+                },
+                None => Pagination::First {
+                    first: first.unwrap_or(100),
+                    after: cursor(after)?,
+                },
+            };
+            print(&ceramic.query(&model, filter, pagination).await?)
+        }
+        Command::Healthcheck => print(&json!({ "status": ceramic.healthcheck().await? })),
+        Command::NodeStatus => print(&ceramic.node_status().await?),
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{Error, Result};
+use ceramic_event::{Cid, StreamId};
+
+/// Identifier of a specific commit of a stream, used to load the stream as of that commit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitId {
+    stream_id: StreamId,
+    commit: Option<Cid>,
+}
+
+impl CommitId {
+    /// Create a commit id for the given commit of a stream
+    pub fn new(stream_id: StreamId, commit: Cid) -> Self {
+        Self {
+            stream_id,
+            commit: Some(commit),
+        }
+    }
+
+    /// Create a commit id for the genesis commit of a stream
+    pub fn genesis(stream_id: StreamId) -> Self {
+        Self {
+            stream_id,
+            commit: None,
+        }
+    }
+
+    /// Stream the commit belongs to
+    pub fn stream_id(&self) -> &StreamId {
+        &self.stream_id
+    }
+
+    /// CID of the commit, or the genesis CID for a genesis commit id
+    pub fn commit(&self) -> &Cid {
+        self.commit.as_ref().unwrap_or(&self.stream_id.cid)
+    }
+
+    /// Binary representation of the commit id, the stream id followed by the commit CID, or a
+    /// single zero byte for the genesis commit
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        let mut bytes = self.stream_id.to_vec().map_err(Error::Encoding)?;
+        match &self.commit {
+            Some(commit) => bytes.extend(commit.to_bytes()),
+            None => bytes.push(0),
+        }
+        Ok(bytes)
+    }
+
+    /// Multibase base36 representation of the commit id, as accepted by the streams endpoint
+    pub fn to_multibase(&self) -> Result<String> {
+        Ok(multibase::encode(
+            multibase::Base::Base36Lower,
+            self.to_vec()?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn should_encode_genesis_commit() {
+        let stream_id =
+            StreamId::from_str("kjzl6hvfrbw6c8apa5yce6ah3fsz9sgrh6upniy0tz8z76gdm169ds3tf8c051t")
+                .unwrap();
+        let commit_id = CommitId::genesis(stream_id.clone());
+        assert_eq!(commit_id.commit(), &stream_id.cid);
+        let (base, bytes) = multibase::decode(commit_id.to_multibase().unwrap()).unwrap();
+        assert_eq!(base, multibase::Base::Base36Lower);
+        let mut expected = stream_id.to_vec().unwrap();
+        expected.push(0);
+        assert_eq!(bytes, expected);
+    }
+}
@@ -0,0 +1,193 @@
+use ceramic_event::{Cid, StreamId};
+
+/// Errors returned by the ceramic http client
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The request could not be sent or the response could not be read
+    #[cfg(feature = "remote")]
+    #[error("transport error: {0}")]
+    Transport(#[from] reqwest::Error),
+    /// A url could not be constructed for the request
+    #[cfg(feature = "remote")]
+    #[error("invalid url: {0}")]
+    Url(#[from] url::ParseError),
+    /// The node responded with an unsuccessful status code
+    #[error("http status {status}: {body}")]
+    Http {
+        /// Status code of the response
+        status: u16,
+        /// Raw body of the response
+        body: String,
+    },
+    /// The node responded with a ceramic api error
+    #[error("{context}: {message}")]
+    Ceramic {
+        /// Operation that was being performed
+        context: String,
+        /// Error message returned by ceramic
+        message: String,
+    },
+    /// The response body could not be decoded
+    #[error("failed to decode response: {source}")]
+    Decode {
+        /// Underlying decode error
+        source: serde_json::Error,
+        /// Raw body of the response
+        body: String,
+    },
+    /// Data could not be serialized or deserialized
+    #[error("serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+    /// An event could not be created or signed
+    #[error("signing error: {0}")]
+    Signing(#[source] anyhow::Error),
+    /// A stream id, cid or other identifier could not be encoded or decoded
+    #[error("encoding error: {0}")]
+    Encoding(#[source] anyhow::Error),
+    /// The stream id was not of the type required by the operation
+    #[error("invalid stream type for {stream_id}, expected {expected}")]
+    InvalidStreamType {
+        /// Stream id that was provided
+        stream_id: Box<StreamId>,
+        /// Kind of stream that was expected
+        expected: &'static str,
+    },
+    /// The stream does not have any commits
+    #[error("No commits found for stream {0}")]
+    NoCommits(StreamId),
+    /// The node could not load a stream requested along with others
+    #[error("stream {0} not found")]
+    StreamNotFound(StreamId),
+    /// The node created a stream with a different id than the one derived locally
+    #[error("expected stream {expected} but the node returned {actual}")]
+    UnexpectedStreamId {
+        /// Stream id derived from the genesis commit
+        expected: Box<StreamId>,
+        /// Stream id returned by the node
+        actual: Box<StreamId>,
+    },
+    /// The anchor service failed to anchor the stream
+    #[error("anchoring stream {0} failed")]
+    AnchorFailed(StreamId),
+    /// The stream was not anchored before the timeout elapsed
+    #[error("timed out waiting for stream {0} to be anchored")]
+    AnchorTimeout(StreamId),
+    /// A model definition is invalid or incompatible with an interface it implements
+    #[error("invalid model {model}: {message}")]
+    InvalidModel {
+        /// Name of the model
+        model: String,
+        /// Reason the model is invalid
+        message: String,
+    },
+    /// The operation relies on stable model ids, which a client signing with a did session does
+    /// not produce, since the capability of the session is part of each genesis
+    #[error("{0} requires a did:key signer, since model ids change with each did session")]
+    SessionUnsupported(&'static str),
+    /// Commits of a stream failed verification
+    #[cfg(feature = "remote")]
+    #[error("stream {} failed verification of {} commits", .0.stream_id, .0.failures.len())]
+    Verification(Box<crate::Verification>),
+    /// The stream was updated since the expected tip was read
+    #[error(
+        "conflicting update to stream {stream_id}, expected tip {expected} but found {actual}"
+    )]
+    Conflict {
+        /// Stream that was being updated
+        stream_id: Box<StreamId>,
+        /// Tip the update was based on
+        expected: Box<Cid>,
+        /// Current tip of the stream
+        actual: Box<Cid>,
+    },
+}
+
+/// Result type for the ceramic http client
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    /// Whether the node responded to the request with an error, rather than the request failing
+    /// to reach it
+    #[cfg(feature = "remote")]
+    pub(crate) fn is_rejected_request(&self) -> bool {
+        matches!(self, Self::Http { .. } | Self::Ceramic { .. })
+    }
+
+    /// Whether the node rejected the admin code of an admin request as expired or already used,
+    /// which it does with a 401 status and an error naming the admin code, rather than the DID
+    #[cfg(feature = "remote")]
+    pub(crate) fn is_rejected_admin_code(&self) -> bool {
+        match self {
+            Self::Http { status: 401, body } => {
+                serde_json::from_str::<crate::api::ErrorResponse>(body)
+                    .is_ok_and(|err| err.error.contains("admin code"))
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn expect_model(stream_id: &StreamId) -> Result<()> {
+        if stream_id.is_model() {
+            Ok(())
+        } else {
+            Err(Self::InvalidStreamType {
+                stream_id: Box::new(stream_id.clone()),
+                expected: "model",
+            })
+        }
+    }
+
+    pub(crate) fn expect_document(stream_id: &StreamId) -> Result<()> {
+        if stream_id.is_document() {
+            Ok(())
+        } else {
+            Err(Self::InvalidStreamType {
+                stream_id: Box::new(stream_id.clone()),
+                expected: "document",
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::StreamsResponseOrError;
+
+    #[test]
+    fn should_resolve_ceramic_error() {
+        let resp: StreamsResponseOrError =
+            serde_json::from_str(r#"{"error":"stream not found"}"#).unwrap();
+        let err = resp.resolve("get").unwrap_err();
+        assert!(matches!(
+            &err,
+            Error::Ceramic { context, message } if context == "get" && message == "stream not found"
+        ));
+        assert_eq!(err.to_string(), "get: stream not found");
+    }
+
+    #[cfg(feature = "remote")]
+    #[test]
+    fn should_detect_rejected_admin_codes() {
+        let unauthorized = |error: &str| Error::Http {
+            status: 401,
+            body: serde_json::json!({ "error": error }).to_string(),
+        };
+        // Messages of the js-ceramic daemon for used and expired admin codes
+        assert!(
+            unauthorized("Unauthorized access: invalid/already used admin code")
+                .is_rejected_admin_code()
+        );
+        assert!(unauthorized(
+            "Unauthorized access: expired admin code - admin codes are only valid for 60 seconds"
+        )
+        .is_rejected_admin_code());
+        assert!(!unauthorized("Unauthorized access: DID is not an admin").is_rejected_admin_code());
+        let bad_request = Error::Http {
+            status: 400,
+            body: r#"{"error":"admin code is missing"}"#.to_string(),
+        };
+        assert!(!bad_request.is_rejected_admin_code());
+    }
+}
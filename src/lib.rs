@@ -3,37 +3,104 @@
 //! This crate provides a client for interacting with the Ceramic HTTP API.
 #![deny(warnings)]
 #![deny(missing_docs)]
+#[cfg(feature = "remote")]
+mod admin;
 /// Structures for working with ceramic http api
 pub mod api;
+#[cfg(feature = "remote")]
+mod apply;
+mod commit_id;
+mod error;
+/// In memory mock of the ceramic http api for testing
+#[cfg(feature = "mock")]
+pub mod mock;
 mod model_definition;
 mod query;
+#[cfg(feature = "remote")]
+mod retry;
+mod session;
+#[cfg(feature = "remote")]
+mod verify;
 
 use ceramic_event::{
     Base64String, Cid, DagCborEncoded, EventArgs, Jws, MultiBase36String, Signer, StreamId,
     StreamIdType,
 };
 use serde::Serialize;
-use std::str::FromStr;
 
 use crate::api::ModelData;
+#[cfg(feature = "remote")]
+pub use apply::{ApplyPlan, PlannedModel};
 pub use ceramic_event;
+pub use commit_id::CommitId;
+pub use error::{Error, Result};
 pub use model_definition::{
     GetRootSchema, ModelAccountRelation, ModelDefinition, ModelRelationDefinition,
-    ModelViewDefinition,
+    ModelViewDefinition, MODEL_VERSION_1, MODEL_VERSION_2,
 };
 pub use query::*;
 pub use schemars;
+pub use session::{Cacao, DidSession};
+#[cfg(feature = "remote")]
+pub use verify::{CommitFailure, Verification, VerificationFailure};
+
+/// Family used in block headers when none is configured
+pub const DEFAULT_FAMILY: &str = "test";
+
+/// Per request overrides of the options configured on a [`CeramicHttpClient`]
+#[derive(Clone, Debug, Default)]
+pub struct RequestOpts {
+    /// Family written to the block header instead of the one configured on the client
+    pub family: Option<String>,
+    /// Options sent to the node, each taking precedence over the one configured on the client
+    pub write: api::WriteOpts,
+}
 
 /// Client for interacting with the Ceramic HTTP API
 #[derive(Clone, Debug)]
 pub struct CeramicHttpClient<S: Signer> {
     signer: S,
+    family: String,
+    anchor: bool,
+    capability: Option<session::Capability>,
+}
+
+impl<S: Signer + Send + Sync> CeramicHttpClient<DidSession<S>> {
+    /// Create a new client that writes on behalf of the issuer of a did session, attaching the
+    /// session capability to every created or updated stream
+    pub fn new_with_session(session: DidSession<S>) -> Self {
+        let capability = session.capability();
+        Self {
+            signer: session,
+            family: DEFAULT_FAMILY.to_string(),
+            anchor: false,
+            capability: Some(capability),
+        }
+    }
 }
 
 impl<S: Signer> CeramicHttpClient<S> {
     /// Create a new client, using a signer and private key
     pub fn new(signer: S) -> Self {
-        Self { signer }
+        Self {
+            signer,
+            family: DEFAULT_FAMILY.to_string(),
+            anchor: false,
+            capability: None,
+        }
+    }
+
+    /// Set the family written to the header of created and updated streams. It can be overridden
+    /// per request with the `family` of [`RequestOpts`].
+    pub fn with_family(mut self, family: impl Into<String>) -> Self {
+        self.family = family.into();
+        self
+    }
+
+    /// Request an anchor of the stream with every created or updated stream
+    pub fn with_anchor(mut self, anchor: bool) -> Self {
+        self.anchor = anchor;
+        self
     }
 
     /// Get the signer for this client
@@ -41,6 +108,48 @@ impl<S: Signer> CeramicHttpClient<S> {
         &self.signer
     }
 
+    /// Get the family written to block headers
+    pub fn family(&self) -> &str {
+        &self.family
+    }
+
+    /// Options sent to the node, with the per request `opts` taking precedence over the client's
+    fn write_opts(&self, opts: &RequestOpts) -> Option<api::WriteOpts> {
+        let anchor = opts.write.anchor.or(self.anchor.then_some(true));
+        let publish = opts.write.publish;
+        (anchor.is_some() || publish.is_some()).then_some(api::WriteOpts { anchor, publish })
+    }
+
+    fn block_header(
+        &self,
+        args: &EventArgs<'_, S>,
+        opts: &RequestOpts,
+    ) -> Result<api::BlockHeader> {
+        let controllers: Vec<_> = args.controllers().map(|c| c.id.clone()).collect();
+        let model = Base64String::from(args.parent().to_vec().map_err(Error::Encoding)?);
+        Ok(api::BlockHeader {
+            family: opts.family.clone().unwrap_or_else(|| self.family.clone()),
+            controllers,
+            model,
+        })
+    }
+
+    /// Jws of a signed commit and its CID, after attaching the capability if there is one
+    async fn commit_jws(&self, jws: Jws, cid: Cid) -> Result<(Jws, Cid)> {
+        match &self.capability {
+            Some(capability) => {
+                let jws = capability.attach(&self.signer, jws).await?;
+                let cid = session::jws_cid(&jws)?;
+                Ok((jws, cid))
+            }
+            None => Ok((jws, cid)),
+        }
+    }
+
+    fn cacao_block(&self) -> Option<Base64String> {
+        self.capability.as_ref().map(|c| c.cacao_block())
+    }
+
     /// Get the streams endpoint
     pub fn streams_endpoint(&self) -> &'static str {
         "/api/v0/streams"
@@ -70,6 +179,16 @@ impl<S: Signer> CeramicHttpClient<S> {
         "/api/v0/admin/models"
     }
 
+    /// Get the multiqueries endpoint
+    pub fn multi_queries_endpoint(&self) -> &'static str {
+        "/api/v0/multiqueries"
+    }
+
+    /// Get the pins endpoint
+    pub fn pins_endpoint(&self) -> &'static str {
+        "/api/v0/pins"
+    }
+
     /// Get the healthcheck endpoint
     pub fn healthcheck_endpoint(&self) -> &'static str {
         "/api/v0/node/healthcheck"
@@ -80,31 +199,42 @@ impl<S: Signer> CeramicHttpClient<S> {
         "/api/v0/admin/status"
     }
 
-    /// Create a serde compatible request for model creation
+    /// Create a serde compatible request for model creation, along with the id of the model
     pub async fn create_model_request(
         &self,
         model: &ModelDefinition,
-    ) -> anyhow::Result<api::CreateRequest<Base64String>> {
+    ) -> Result<(api::CreateRequest<Base64String>, StreamId)> {
+        self.create_model_request_with_opts(model, &Default::default())
+            .await
+    }
+
+    /// Create a serde compatible request for model creation with per request options, along with
+    /// the id of the model
+    pub async fn create_model_request_with_opts(
+        &self,
+        model: &ModelDefinition,
+        opts: &RequestOpts,
+    ) -> Result<(api::CreateRequest<Base64String>, StreamId)> {
         let args = EventArgs::new(&self.signer);
-        let commit = args.init_with_data(&model).await?;
-        let controllers: Vec<_> = args.controllers().map(|c| c.id.clone()).collect();
+        let commit = args.init_with_data(&model).await.map_err(Error::Signing)?;
         let data = Base64String::from(commit.linked_block.as_ref());
-        let model = Base64String::from(args.parent().to_vec()?);
-
-        Ok(api::CreateRequest {
+        let (jws, cid) = self.commit_jws(commit.jws, commit.cid).await?;
+        let req = api::CreateRequest {
             r#type: StreamIdType::Model,
             block: api::BlockData {
-                header: api::BlockHeader {
-                    family: "test".to_string(),
-                    controllers,
-                    model,
-                },
+                header: self.block_header(&args, opts)?,
                 linked_block: Some(data.clone()),
-                jws: Some(commit.jws),
+                jws: Some(jws),
                 data: Some(data),
-                cacao_block: None,
+                cacao_block: self.cacao_block(),
             },
-        })
+            opts: self.write_opts(opts),
+        };
+        let stream_id = StreamId {
+            r#type: StreamIdType::Model,
+            cid,
+        };
+        Ok((req, stream_id))
     }
 
     /// Create a serde compatible request for model indexing
@@ -112,18 +242,54 @@ impl<S: Signer> CeramicHttpClient<S> {
         &self,
         model_id: &StreamId,
         code: &str,
-    ) -> anyhow::Result<api::AdminApiRequest> {
+    ) -> Result<api::AdminApiRequest> {
+        self.create_index_models_request(std::slice::from_ref(model_id), code)
+            .await
+    }
+
+    /// Create a serde compatible request for indexing a batch of models, sent as a POST to the
+    /// index endpoint
+    pub async fn create_index_models_request(
+        &self,
+        model_ids: &[StreamId],
+        code: &str,
+    ) -> Result<api::AdminApiRequest> {
+        self.model_data_request(model_ids, code).await
+    }
+
+    /// Create a serde compatible request for unindexing a batch of models, sent as a DELETE to
+    /// the index endpoint
+    pub async fn create_unindex_models_request(
+        &self,
+        model_ids: &[StreamId],
+        code: &str,
+    ) -> Result<api::AdminApiRequest> {
+        self.model_data_request(model_ids, code).await
+    }
+
+    /// Signed admin request listing models, whose meaning depends on the http method it is sent
+    /// with
+    async fn model_data_request(
+        &self,
+        model_ids: &[StreamId],
+        code: &str,
+    ) -> Result<api::AdminApiRequest> {
         let data = api::IndexModelData {
-            models: vec![ModelData {
-                model: model_id.clone(),
-            }],
+            models: model_ids
+                .iter()
+                .map(|model| ModelData {
+                    model: model.clone(),
+                })
+                .collect(),
         };
         let req = api::AdminApiPayload {
             code: code.to_string(),
             request_path: self.index_endpoint().to_string(),
             request_body: data,
         };
-        let jws = Jws::for_data(&self.signer, &req).await?;
+        let jws = Jws::for_data(&self.signer, &req)
+            .await
+            .map_err(Error::Signing)?;
         api::AdminApiRequest::try_from(jws)
     }
 
@@ -131,73 +297,197 @@ impl<S: Signer> CeramicHttpClient<S> {
     pub async fn create_list_indexed_models_request(
         &self,
         code: &str,
-    ) -> anyhow::Result<api::AdminApiRequest> {
+    ) -> Result<api::AdminApiRequest> {
         let data = api::ListIndexedModelsRequest {};
         let req = api::AdminApiPayload {
             code: code.to_string(),
             request_path: self.models_endpoint().to_string(),
             request_body: data,
         };
-        let jws = Jws::for_data(&self.signer, &req).await?;
+        let jws = Jws::for_data(&self.signer, &req)
+            .await
+            .map_err(Error::Signing)?;
         api::AdminApiRequest::try_from(jws)
     }
 
-    /// Create a serde compatible request for a single instance per account creation of a model
+    /// Create a serde compatible request for a single instance per account creation of a model,
+    /// along with the deterministic id of the instance
     pub async fn create_single_instance_request(
         &self,
         model_id: &StreamId,
-    ) -> anyhow::Result<api::CreateRequest<DagCborEncoded>> {
-        if !model_id.is_model() {
-            anyhow::bail!("StreamId was not a model");
-        }
+    ) -> Result<(api::CreateRequest<DagCborEncoded>, StreamId)> {
+        self.create_single_instance_request_with_opts(model_id, &Default::default())
+            .await
+    }
+
+    /// Create a serde compatible request for a single instance per account creation of a model
+    /// with per request options, along with the deterministic id of the instance
+    pub async fn create_single_instance_request_with_opts(
+        &self,
+        model_id: &StreamId,
+        opts: &RequestOpts,
+    ) -> Result<(api::CreateRequest<DagCborEncoded>, StreamId)> {
+        Error::expect_model(model_id)?;
         let args = EventArgs::new_with_parent(&self.signer, model_id);
-        let commit = args.init()?;
-        let controllers: Vec<_> = args.controllers().map(|c| c.id.clone()).collect();
-        let model = Base64String::from(model_id.to_vec()?);
-        Ok(api::CreateRequest {
+        let commit = args.init().map_err(Error::Signing)?;
+        let req = api::CreateRequest {
             r#type: StreamIdType::ModelInstanceDocument,
             block: api::BlockData {
-                header: api::BlockHeader {
-                    family: "test".to_string(),
-                    controllers,
-                    model,
-                },
+                header: self.block_header(&args, opts)?,
                 linked_block: None,
                 jws: None,
                 data: Some(commit.encoded),
                 cacao_block: None,
             },
+            opts: self.write_opts(opts),
+        };
+        let stream_id = StreamId {
+            r#type: StreamIdType::ModelInstanceDocument,
+            cid: commit.cid,
+        };
+        Ok((req, stream_id))
+    }
+
+    /// Create a serde compatible request for an instance of a model with a set account relation,
+    /// along with the deterministic id of the instance for the values of its unique fields
+    pub async fn create_set_instance_request(
+        &self,
+        model_id: &StreamId,
+        unique: &[String],
+    ) -> Result<(api::CreateRequest<Base64String>, StreamId)> {
+        self.create_set_instance_request_with_opts(model_id, unique, &Default::default())
+            .await
+    }
+
+    /// Create a serde compatible request for an instance of a model with a set account relation
+    /// with per request options, along with the deterministic id of the instance
+    pub async fn create_set_instance_request_with_opts(
+        &self,
+        model_id: &StreamId,
+        unique: &[String],
+        opts: &RequestOpts,
+    ) -> Result<(api::CreateRequest<Base64String>, StreamId)> {
+        Error::expect_model(model_id)?;
+        let args = EventArgs::new_with_parent(&self.signer, model_id);
+        let (genesis, stream_id) = self.set_instance_genesis(&args, unique)?;
+        let req = api::CreateRequest {
+            r#type: StreamIdType::ModelInstanceDocument,
+            block: api::BlockData {
+                header: self.block_header(&args, opts)?,
+                linked_block: None,
+                jws: None,
+                data: Some(Base64String::from(genesis)),
+                cacao_block: None,
+            },
+            opts: self.write_opts(opts),
+        };
+        Ok((req, stream_id))
+    }
+
+    /// Deterministic stream id of the instance of a model with a set account relation controlled
+    /// by this client, for the values of its unique fields in the order the model declares them.
+    /// The number of values is not checked against the model, see
+    /// [`ModelDefinition::validate_unique`].
+    pub fn set_instance_stream_id(
+        &self,
+        model_id: &StreamId,
+        unique: &[String],
+    ) -> Result<StreamId> {
+        Error::expect_model(model_id)?;
+        let args = EventArgs::new_with_parent(&self.signer, model_id);
+        Ok(self.set_instance_genesis(&args, unique)?.1)
+    }
+
+    /// Unsigned genesis of a set instance, with the unique field values in its header
+    fn set_instance_genesis(
+        &self,
+        args: &EventArgs<'_, S>,
+        unique: &[String],
+    ) -> Result<(Vec<u8>, StreamId)> {
+        use ciborium::value::Value as Cbor;
+        if unique.is_empty() {
+            return Err(Error::Encoding(anyhow::anyhow!(
+                "set instance requires unique values"
+            )));
+        }
+        let controllers = args
+            .controllers()
+            .map(|c| Cbor::Text(c.id.clone()))
+            .collect();
+        let model = args.parent().to_vec().map_err(Error::Encoding)?;
+        let unique = unique.iter().cloned().map(Cbor::Text).collect();
+        // Keys are in dag-cbor canonical order, shortest first
+        let header = Cbor::Map(vec![
+            (Cbor::Text("sep".into()), Cbor::Text("model".into())),
+            (Cbor::Text("model".into()), Cbor::Bytes(model)),
+            (Cbor::Text("unique".into()), Cbor::Array(unique)),
+            (Cbor::Text("controllers".into()), Cbor::Array(controllers)),
+        ]);
+        let genesis = Cbor::Map(vec![
+            (Cbor::Text("data".into()), Cbor::Null),
+            (Cbor::Text("header".into()), header),
+        ]);
+        let mut encoded = Vec::new();
+        ciborium::ser::into_writer(&genesis, &mut encoded)
+            .map_err(|e| Error::Encoding(anyhow::anyhow!("Invalid genesis: {}", e)))?;
+        let stream_id = StreamId {
+            r#type: StreamIdType::ModelInstanceDocument,
+            cid: session::dag_cbor_cid(&encoded)?,
+        };
+        Ok((encoded, stream_id))
+    }
+
+    /// Deterministic stream id of the single instance of a model controlled by this client
+    pub fn single_instance_stream_id(&self, model_id: &StreamId) -> Result<StreamId> {
+        Error::expect_model(model_id)?;
+        let args = EventArgs::new_with_parent(&self.signer, model_id);
+        let commit = args.init().map_err(Error::Signing)?;
+        Ok(StreamId {
+            r#type: StreamIdType::ModelInstanceDocument,
+            cid: commit.cid,
         })
     }
 
-    /// Create a serde compatible request for a list instance per account creation of a model
+    /// Create a serde compatible request for a list instance per account creation of a model,
+    /// along with the id of the instance
     pub async fn create_list_instance_request<T: Serialize>(
         &self,
         model_id: &StreamId,
         data: T,
-    ) -> anyhow::Result<api::CreateRequest<Base64String>> {
-        if !model_id.is_model() {
-            anyhow::bail!("StreamId was not a model");
-        }
+    ) -> Result<(api::CreateRequest<Base64String>, StreamId)> {
+        self.create_list_instance_request_with_opts(model_id, data, &Default::default())
+            .await
+    }
+
+    /// Create a serde compatible request for a list instance per account creation of a model with
+    /// per request options, along with the id of the instance
+    pub async fn create_list_instance_request_with_opts<T: Serialize>(
+        &self,
+        model_id: &StreamId,
+        data: T,
+        opts: &RequestOpts,
+    ) -> Result<(api::CreateRequest<Base64String>, StreamId)> {
+        Error::expect_model(model_id)?;
         let args = EventArgs::new_with_parent(&self.signer, model_id);
-        let commit = args.init_with_data(&data).await?;
-        let controllers: Vec<_> = args.controllers().map(|c| c.id.clone()).collect();
+        let commit = args.init_with_data(&data).await.map_err(Error::Signing)?;
         let data = Base64String::from(commit.linked_block.as_ref());
-        let model = Base64String::from(model_id.to_vec()?);
-        Ok(api::CreateRequest {
+        let (jws, cid) = self.commit_jws(commit.jws, commit.cid).await?;
+        let req = api::CreateRequest {
             r#type: StreamIdType::ModelInstanceDocument,
             block: api::BlockData {
-                header: api::BlockHeader {
-                    family: "test".to_string(),
-                    controllers,
-                    model,
-                },
+                header: self.block_header(&args, opts)?,
                 linked_block: Some(data.clone()),
-                jws: Some(commit.jws),
+                jws: Some(jws),
                 data: Some(data),
-                cacao_block: None,
+                cacao_block: self.cacao_block(),
             },
-        })
+            opts: self.write_opts(opts),
+        };
+        let stream_id = StreamId {
+            r#type: StreamIdType::ModelInstanceDocument,
+            cid,
+        };
+        Ok((req, stream_id))
     }
 
     /// Create a serde compatible request to update specific parts an existing model instance
@@ -206,36 +496,54 @@ impl<S: Signer> CeramicHttpClient<S> {
         model: &StreamId,
         get: &api::StreamsResponse,
         patch: json_patch::Patch,
-    ) -> anyhow::Result<api::UpdateRequest> {
-        if !get.stream_id.is_document() {
-            anyhow::bail!("StreamId was not a document");
-        }
-        if let Some(tip) = get.state.as_ref().and_then(|s| s.log.last()) {
-            let tip = Cid::from_str(tip.cid.as_ref())?;
-            let args = EventArgs::new_with_parent(&self.signer, model);
-            let commit = args.update(&get.stream_id.cid, &tip, &patch).await?;
-            let controllers: Vec<_> = args.controllers().map(|c| c.id.clone()).collect();
-            let data = Base64String::from(commit.linked_block.as_ref());
-            let model = Base64String::from(model.to_vec()?);
-            let stream = MultiBase36String::try_from(&get.stream_id)?;
-            Ok(api::UpdateRequest {
-                r#type: StreamIdType::ModelInstanceDocument,
-                block: api::BlockData {
-                    header: api::BlockHeader {
-                        family: "test".to_string(),
-                        controllers,
-                        model,
-                    },
-                    linked_block: Some(data.clone()),
-                    jws: Some(commit.jws),
-                    data: Some(data),
-                    cacao_block: None,
-                },
-                stream_id: stream,
-            })
-        } else {
-            Err(anyhow::anyhow!("No commits found for stream ",))
-        }
+    ) -> Result<api::UpdateRequest> {
+        self.create_update_request_with_opts(model, get, patch, &Default::default())
+            .await
+    }
+
+    /// Create a serde compatible request to update specific parts an existing model instance with
+    /// per request options
+    pub async fn create_update_request_with_opts(
+        &self,
+        model: &StreamId,
+        get: &api::StreamsResponse,
+        patch: json_patch::Patch,
+        opts: &RequestOpts,
+    ) -> Result<api::UpdateRequest> {
+        Ok(self.update_commit(model, get, patch, opts).await?.0)
+    }
+
+    /// Update request along with the cid of its commit
+    async fn update_commit(
+        &self,
+        model: &StreamId,
+        get: &api::StreamsResponse,
+        patch: json_patch::Patch,
+        opts: &RequestOpts,
+    ) -> Result<(api::UpdateRequest, Cid)> {
+        Error::expect_document(&get.stream_id)?;
+        let tip = get.tip()?;
+        let args = EventArgs::new_with_parent(&self.signer, model);
+        let commit = args
+            .update(&get.stream_id.cid, &tip, &patch)
+            .await
+            .map_err(Error::Signing)?;
+        let data = Base64String::from(commit.linked_block.as_ref());
+        let stream = MultiBase36String::try_from(&get.stream_id).map_err(Error::Encoding)?;
+        let (jws, cid) = self.commit_jws(commit.jws, commit.cid).await?;
+        let req = api::UpdateRequest {
+            r#type: StreamIdType::ModelInstanceDocument,
+            block: api::BlockData {
+                header: self.block_header(&args, opts)?,
+                linked_block: Some(data.clone()),
+                jws: Some(jws),
+                data: Some(data),
+                cacao_block: self.cacao_block(),
+            },
+            stream_id: stream,
+            opts: self.write_opts(opts),
+        };
+        Ok((req, cid))
     }
 
     /// Create a serde compatible request to replace an existing model instance completely
@@ -244,14 +552,43 @@ impl<S: Signer> CeramicHttpClient<S> {
         model: &StreamId,
         get: &api::StreamsResponse,
         data: T,
-    ) -> anyhow::Result<api::UpdateRequest> {
+    ) -> Result<api::UpdateRequest> {
+        self.create_replace_request_with_opts(model, get, data, &Default::default())
+            .await
+    }
+
+    /// Create a serde compatible request to replace an existing model instance completely with
+    /// per request options
+    pub async fn create_replace_request_with_opts<T: Serialize>(
+        &self,
+        model: &StreamId,
+        get: &api::StreamsResponse,
+        data: T,
+        opts: &RequestOpts,
+    ) -> Result<api::UpdateRequest> {
+        Ok(self.replace_commit(model, get, data, opts).await?.0)
+    }
+
+    /// Replace request along with the cid of its commit
+    async fn replace_commit<T: Serialize>(
+        &self,
+        model: &StreamId,
+        get: &api::StreamsResponse,
+        data: T,
+        opts: &RequestOpts,
+    ) -> Result<(api::UpdateRequest, Cid)> {
         let data = serde_json::to_value(data)?;
-        let diff = if let Some(existing) = get.state.as_ref().map(|st| &st.content) {
+        let existing = get
+            .state
+            .as_ref()
+            .map(|st| &st.content)
+            .filter(|content| !content.is_null());
+        let diff = if let Some(existing) = existing {
             json_patch::diff(existing, &data)
         } else {
             json_patch::diff(&serde_json::json!({}), &data)
         };
-        self.create_update_request(model, get, diff).await
+        self.update_commit(model, get, diff, opts).await
     }
 
     /// Create a serde compatible request to query model instances
@@ -260,7 +597,7 @@ impl<S: Signer> CeramicHttpClient<S> {
         model: &StreamId,
         query: Option<FilterQuery>,
         pagination: api::Pagination,
-    ) -> anyhow::Result<api::QueryRequest> {
+    ) -> Result<api::QueryRequest> {
         Ok(api::QueryRequest {
             model: model.clone(),
             account: self.signer.id().id.clone(),
@@ -269,22 +606,46 @@ impl<S: Signer> CeramicHttpClient<S> {
         })
     }
 
+    /// Create a serde compatible request to load many streams at once
+    pub async fn create_multi_query_request(
+        &self,
+        queries: Vec<api::MultiQuery>,
+    ) -> Result<api::MultiQueryRequest> {
+        Ok(api::MultiQueryRequest { queries })
+    }
+
+    /// Create a serde compatible request to pin a stream, pinning it again if `force` is set
+    pub async fn create_pin_request(&self, force: bool) -> Result<api::PinRequest> {
+        Ok(api::PinRequest {
+            force: force.then_some(true),
+        })
+    }
+
+    /// Create a serde compatible request to unpin a stream
+    pub async fn create_unpin_request(&self) -> Result<api::PinRequest> {
+        Ok(api::PinRequest::default())
+    }
+
+    /// Create a serde compatible request to list pinned streams
+    pub async fn create_list_pins_request(&self) -> Result<api::ListPinsRequest> {
+        Ok(api::ListPinsRequest {})
+    }
+
     /// Create a serde compatible request to check node health
-    pub async fn create_healthcheck_request(&self) -> anyhow::Result<api::HealthcheckRequest> {
+    pub async fn create_healthcheck_request(&self) -> Result<api::HealthcheckRequest> {
         Ok(api::HealthcheckRequest {})
     }
     /// Create a serde compatible request for the node status
-    pub async fn create_node_status_request(
-        &self,
-        code: &str,
-    ) -> anyhow::Result<api::AdminApiRequest> {
+    pub async fn create_node_status_request(&self, code: &str) -> Result<api::AdminApiRequest> {
         let data = api::NodeStatusRequest {};
         let req = api::AdminApiPayload {
             code: code.to_string(),
             request_path: self.node_status_endpoint().to_string(),
             request_body: data,
         };
-        let jws = Jws::for_data(&self.signer, &req).await?;
+        let jws = Jws::for_data(&self.signer, &req)
+            .await
+            .map_err(Error::Signing)?;
         api::AdminApiRequest::try_from(jws)
     }
 }
@@ -293,9 +654,20 @@ impl<S: Signer> CeramicHttpClient<S> {
 #[cfg(feature = "remote")]
 pub mod remote {
     use super::*;
+    use crate::admin::AdminSession;
     use crate::api::Pagination;
+    use crate::apply::{ApplyPlan, PlannedModel};
     use crate::query::FilterQuery;
+    pub use crate::retry::{Idempotency, RetryPolicy};
+    use crate::{verify, Verification};
+    use futures::{future, stream, Stream, StreamExt, TryStreamExt};
+    use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+    use reqwest::Method;
     use serde::de::DeserializeOwned;
+    use std::collections::{HashMap, HashSet};
+    use std::future::Future;
+    use std::str::FromStr;
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
     pub use url::{ParseError, Url};
 
     #[derive(Clone)]
@@ -304,111 +676,668 @@ pub mod remote {
         cli: CeramicHttpClient<S>,
         remote: reqwest::Client,
         url: Url,
+        retry: RetryPolicy,
+        headers: HeaderMap,
+        verify: bool,
+        admin: AdminSession,
+    }
+
+    /// Builder for a ceramic remote http client
+    pub struct CeramicRemoteHttpClientBuilder<S: Signer> {
+        cli: CeramicHttpClient<S>,
+        url: Url,
+        remote: Option<reqwest::Client>,
+        timeout: Option<Duration>,
+        connect_timeout: Option<Duration>,
+        user_agent: Option<String>,
+        proxies: Vec<reqwest::Proxy>,
+        root_certificates: Vec<reqwest::Certificate>,
+        retry: RetryPolicy,
+        headers: HeaderMap,
+        verify: bool,
+    }
+
+    impl<S: Signer> CeramicRemoteHttpClientBuilder<S> {
+        /// Create a new builder for a signer and url
+        pub fn new(signer: S, remote: Url) -> Self {
+            Self::from_client(CeramicHttpClient::new(signer), remote)
+        }
+
+        /// Create a new builder wrapping an existing client and url
+        pub fn from_client(cli: CeramicHttpClient<S>, remote: Url) -> Self {
+            Self {
+                cli,
+                url: remote,
+                remote: None,
+                timeout: None,
+                connect_timeout: None,
+                user_agent: None,
+                proxies: Vec::new(),
+                root_certificates: Vec::new(),
+                retry: RetryPolicy::default(),
+                headers: HeaderMap::new(),
+                verify: false,
+            }
+        }
+
+        /// Use an existing reqwest client. Timeouts, user agent, proxies and root certificates
+        /// configured on this builder are ignored, as they must be set on the provided client.
+        pub fn http_client(mut self, remote: reqwest::Client) -> Self {
+            self.remote = Some(remote);
+            self
+        }
+
+        /// Timeout for each request, from connecting until the response body is read
+        pub fn timeout(mut self, timeout: Duration) -> Self {
+            self.timeout = Some(timeout);
+            self
+        }
+
+        /// Timeout for connecting to the remote ceramic
+        pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+            self.connect_timeout = Some(timeout);
+            self
+        }
+
+        /// User agent sent with every request
+        pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+            self.user_agent = Some(user_agent.into());
+            self
+        }
+
+        /// Proxy requests through the given proxy
+        pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+            self.proxies.push(proxy);
+            self
+        }
+
+        /// Trust an additional root certificate
+        pub fn root_certificate(mut self, certificate: reqwest::Certificate) -> Self {
+            self.root_certificates.push(certificate);
+            self
+        }
+
+        /// Header sent with every request, unless the request sets the same header itself
+        pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+            self.headers.insert(name, value);
+            self
+        }
+
+        /// Headers sent with every request, unless the request sets the same header itself
+        pub fn headers(mut self, headers: HeaderMap) -> Self {
+            self.headers.extend(headers);
+            self
+        }
+
+        /// Policy used to retry failed requests
+        pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+            self.retry = retry;
+            self
+        }
+
+        /// Verify the signatures and log of streams returned by `get` and `query`
+        pub fn verify(mut self, verify: bool) -> Self {
+            self.verify = verify;
+            self
+        }
+
+        /// Family written to the header of created and updated streams
+        pub fn family(mut self, family: impl Into<String>) -> Self {
+            self.cli = self.cli.with_family(family);
+            self
+        }
+
+        /// Request an anchor of the stream with every created or updated stream
+        pub fn anchor(mut self, anchor: bool) -> Self {
+            self.cli = self.cli.with_anchor(anchor);
+            self
+        }
+
+        /// Build the remote client
+        pub fn build(self) -> Result<CeramicRemoteHttpClient<S>> {
+            let remote = match self.remote {
+                Some(remote) => remote,
+                None => {
+                    let mut builder = reqwest::Client::builder();
+                    if let Some(timeout) = self.timeout {
+                        builder = builder.timeout(timeout);
+                    }
+                    if let Some(timeout) = self.connect_timeout {
+                        builder = builder.connect_timeout(timeout);
+                    }
+                    if let Some(user_agent) = self.user_agent {
+                        builder = builder.user_agent(user_agent);
+                    }
+                    for proxy in self.proxies {
+                        builder = builder.proxy(proxy);
+                    }
+                    for certificate in self.root_certificates {
+                        builder = builder.add_root_certificate(certificate);
+                    }
+                    builder.build()?
+                }
+            };
+            Ok(CeramicRemoteHttpClient {
+                cli: self.cli,
+                remote,
+                url: self.url,
+                retry: self.retry,
+                headers: self.headers,
+                verify: self.verify,
+                admin: AdminSession::default(),
+            })
+        }
+    }
+
+    fn typed_document<T: DeserializeOwned>(
+        edge: api::QueryEdge,
+    ) -> Result<api::TypedQueryDocument<T>> {
+        Ok(api::TypedQueryDocument {
+            document: serde_json::from_value(edge.node.content)?,
+            commits: edge.node.log,
+        })
+    }
+
+    /// Read the body of a response, turning error statuses and ceramic error bodies into
+    /// `Error`s and decoding successful bodies as json
+    async fn decode<T: DeserializeOwned>(resp: reqwest::Response, context: &str) -> Result<T> {
+        let status = resp.status();
+        let body = resp.text().await?;
+        if !status.is_success() {
+            return match serde_json::from_str::<api::ErrorResponse>(&body) {
+                Ok(err) => Err(Error::Ceramic {
+                    context: context.to_string(),
+                    message: err.error,
+                }),
+                Err(_) => Err(Error::Http {
+                    status: status.as_u16(),
+                    body,
+                }),
+            };
+        }
+        serde_json::from_str(&body).map_err(|source| Error::Decode { source, body })
+    }
+
+    /// Keep the status of an admin response the node rejected as unauthorized, which is how it
+    /// rejects expired or used admin codes
+    async fn admin_response(resp: reqwest::Response) -> Result<reqwest::Response> {
+        let status = resp.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(Error::Http {
+                status: status.as_u16(),
+                body: resp.text().await?,
+            });
+        }
+        Ok(resp)
+    }
+
+    impl<S: Signer + Send + Sync> CeramicRemoteHttpClient<DidSession<S>> {
+        /// Create a new ceramic remote http client writing on behalf of the issuer of a did
+        /// session
+        pub fn new_with_session(session: DidSession<S>, remote: Url) -> Self {
+            Self::from_client(CeramicHttpClient::new_with_session(session), remote)
+        }
     }
 
     impl<S: Signer> CeramicRemoteHttpClient<S> {
         /// Create a new ceramic remote http client for a signer, private key, and url
         pub fn new(signer: S, remote: Url) -> Self {
+            Self::from_client(CeramicHttpClient::new(signer), remote)
+        }
+
+        fn from_client(cli: CeramicHttpClient<S>, remote: Url) -> Self {
             Self {
-                cli: CeramicHttpClient::new(signer),
+                cli,
                 remote: reqwest::Client::new(),
                 url: remote,
+                retry: RetryPolicy::default(),
+                headers: HeaderMap::new(),
+                verify: false,
+                admin: AdminSession::default(),
             }
         }
 
+        /// Create a builder to configure the http client, headers and retries used
+        pub fn builder(signer: S, remote: Url) -> CeramicRemoteHttpClientBuilder<S> {
+            CeramicRemoteHttpClientBuilder::new(signer, remote)
+        }
+
+        /// Set the family written to the header of created and updated streams
+        pub fn with_family(mut self, family: impl Into<String>) -> Self {
+            self.cli = self.cli.with_family(family);
+            self
+        }
+
+        /// Request an anchor of the stream with every created or updated stream
+        pub fn with_anchor(mut self, anchor: bool) -> Self {
+            self.cli = self.cli.with_anchor(anchor);
+            self
+        }
+
+        /// Set the policy used to retry failed requests
+        pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+            self.retry = retry;
+            self
+        }
+
+        /// Set whether to verify the signatures and log of streams returned by `get` and
+        /// `query`, failing with `Error::Verification` if any commit fails
+        pub fn with_verification(mut self, verify: bool) -> Self {
+            self.verify = verify;
+            self
+        }
+
         /// Access the underlying client
         pub fn client(&self) -> &CeramicHttpClient<S> {
             &self.cli
         }
 
         /// Utility function to get a url for this client's base url, given a path
-        pub fn url_for_path(&self, path: &str) -> anyhow::Result<url::Url> {
+        pub fn url_for_path(&self, path: &str) -> Result<url::Url> {
             let u = self.url.join(path)?;
             Ok(u)
         }
 
-        /// Create a model on the remote ceramic
-        pub async fn create_model(&self, model: &ModelDefinition) -> anyhow::Result<StreamId> {
-            let req = self.cli.create_model_request(model).await?;
-            let resp: api::StreamsResponseOrError = self
-                .remote
-                .post(self.url_for_path(self.cli.streams_endpoint())?)
-                .json(&req)
-                .send()
-                .await?
-                .json()
-                .await?;
-            Ok(resp.resolve("create_model")?.stream_id)
+        /// Send a request, retrying according to the retry policy of this client
+        async fn send(
+            &self,
+            req: reqwest::RequestBuilder,
+            idempotency: Idempotency,
+        ) -> Result<reqwest::Response> {
+            let mut req = req.build()?;
+            for (name, value) in &self.headers {
+                if !req.headers().contains_key(name) {
+                    req.headers_mut().insert(name, value.clone());
+                }
+            }
+            let mut retry = 0;
+            loop {
+                let last = retry + 1 >= self.retry.max_attempts;
+                let attempt = match req.try_clone() {
+                    Some(attempt) if !last => attempt,
+                    _ => return Ok(self.remote.execute(req).await?),
+                };
+                match self.remote.execute(attempt).await {
+                    Ok(resp) if !self.retry.retry_status(resp.status().as_u16(), idempotency) => {
+                        return Ok(resp)
+                    }
+                    Err(err) if !self.retry.retry_error(&err, idempotency) => {
+                        return Err(err.into())
+                    }
+                    _ => {}
+                }
+                retry += 1;
+                tokio::time::sleep(self.retry.backoff(retry)).await;
+            }
         }
 
-        /// Index a model on the remote ceramic
-        pub async fn index_model(&self, model_id: &StreamId) -> anyhow::Result<()> {
-            let resp: api::AdminCodeResponse = self
-                .remote
-                .get(self.url_for_path(self.cli.admin_code_endpoint())?)
-                .send()
-                .await?
-                .json()
-                .await?;
-            let req = self
-                .cli
-                .create_index_model_request(model_id, &resp.code)
-                .await?;
+        /// Admin code for signing an admin request, taking the code fetched ahead while it is
+        /// valid
+        async fn admin_code(&self) -> Result<String> {
+            match self.admin.take() {
+                Some(code) => Ok(code),
+                None => self.fetch_admin_code().await,
+            }
+        }
+
+        async fn fetch_admin_code(&self) -> Result<String> {
             let resp = self
-                .remote
-                .post(self.url_for_path(self.cli.index_endpoint())?)
-                .json(&req)
-                .send()
+                .send(
+                    self.remote
+                        .get(self.url_for_path(self.cli.admin_code_endpoint())?),
+                    Idempotency::Idempotent,
+                )
                 .await?;
-            if resp.status().is_success() {
-                Ok(())
+            let resp: api::AdminCodeResponse = decode(resp, "admin_code").await?;
+            Ok(resp.code)
+        }
+
+        /// Run an admin request with a single use admin code. When `prefetch` is set, because
+        /// another admin request of a batch follows, the code for that request is fetched
+        /// meanwhile. Retries once with a new code if the node rejects the code as expired or
+        /// already used.
+        async fn with_admin_code<T, F, Fut>(&self, prefetch: bool, request: F) -> Result<T>
+        where
+            F: Fn(String) -> Fut,
+            Fut: Future<Output = Result<T>>,
+        {
+            let code = self.admin_code().await?;
+            let resp = if prefetch {
+                let (resp, next) = future::join(request(code), self.fetch_admin_code()).await;
+                if let Ok(next) = next {
+                    self.admin.set(next);
+                }
+                resp
             } else {
-                anyhow::bail!("{}", resp.text().await?);
+                request(code).await
+            };
+            match resp {
+                Err(e) if e.is_rejected_admin_code() => request(self.admin_code().await?).await,
+                resp => resp,
+            }
+        }
+
+        /// Post a genesis commit, checking that the node created the stream with the given id
+        async fn post_genesis<T: Serialize>(
+            &self,
+            req: &api::CreateRequest<T>,
+            stream_id: &StreamId,
+            context: &str,
+        ) -> Result<api::StreamsResponse> {
+            let resp = self
+                .send(
+                    self.remote
+                        .post(self.url_for_path(self.cli.streams_endpoint())?)
+                        .json(req),
+                    Idempotency::Idempotent,
+                )
+                .await?;
+            let resp: api::StreamsResponseOrError = decode(resp, context).await?;
+            let resp = resp.resolve(context)?;
+            if &resp.stream_id != stream_id {
+                return Err(Error::UnexpectedStreamId {
+                    expected: Box::new(stream_id.clone()),
+                    actual: Box::new(resp.stream_id),
+                });
+            }
+            Ok(resp)
+        }
+
+        /// Create a model on the remote ceramic, after validating it against the interfaces it
+        /// implements
+        pub async fn create_model(&self, model: &ModelDefinition) -> Result<StreamId> {
+            let mut interfaces = Vec::with_capacity(model.implements().len());
+            for interface in model.implements() {
+                interfaces.push(self.get_model(interface).await?);
+            }
+            model.validate(&interfaces)?;
+            let (req, stream_id) = self.cli.create_model_request(model).await?;
+            let resp = self.post_genesis(&req, &stream_id, "create_model").await?;
+            Ok(resp.stream_id)
+        }
+
+        /// Index a model on the remote ceramic
+        pub async fn index_model(&self, model_id: &StreamId) -> Result<()> {
+            self.model_data(std::slice::from_ref(model_id), Method::POST, false)
+                .await
+        }
+
+        /// Stop indexing a model on the remote ceramic
+        pub async fn unindex_model(&self, model_id: &StreamId) -> Result<()> {
+            self.model_data(std::slice::from_ref(model_id), Method::DELETE, false)
+                .await
+        }
+
+        /// Index a batch of models on the remote ceramic, returning the outcome for each model.
+        /// The node applies a batch as a whole, so if it rejects the batch each model is sent
+        /// alone to find the ones it rejects.
+        pub async fn index_models(
+            &self,
+            model_ids: &[StreamId],
+        ) -> Result<Vec<api::ModelIndexOutcome>> {
+            self.model_data_batch(model_ids, Method::POST).await
+        }
+
+        /// Stop indexing a batch of models on the remote ceramic, returning the outcome for each
+        /// model in the same way as `index_models`
+        pub async fn unindex_models(
+            &self,
+            model_ids: &[StreamId],
+        ) -> Result<Vec<api::ModelIndexOutcome>> {
+            self.model_data_batch(model_ids, Method::DELETE).await
+        }
+
+        async fn model_data_batch(
+            &self,
+            model_ids: &[StreamId],
+            method: Method,
+        ) -> Result<Vec<api::ModelIndexOutcome>> {
+            let outcome = |model: &StreamId, result| api::ModelIndexOutcome {
+                model: model.clone(),
+                result,
+            };
+            match self.model_data(model_ids, method.clone(), false).await {
+                Ok(()) => Ok(model_ids.iter().map(|m| outcome(m, Ok(()))).collect()),
+                Err(e) if !e.is_rejected_request() => Err(e),
+                Err(e) if model_ids.len() == 1 => Ok(vec![outcome(&model_ids[0], Err(e))]),
+                Err(_) => {
+                    let mut outcomes = Vec::with_capacity(model_ids.len());
+                    for (i, model) in model_ids.iter().enumerate() {
+                        let prefetch = i + 1 < model_ids.len();
+                        let result = self
+                            .model_data(std::slice::from_ref(model), method.clone(), prefetch)
+                            .await;
+                        match result {
+                            Err(e) if !e.is_rejected_request() => return Err(e),
+                            result => outcomes.push(outcome(model, result)),
+                        }
+                    }
+                    Ok(outcomes)
+                }
             }
         }
 
+        /// Send models to the index endpoint, indexing them with POST or unindexing them with
+        /// DELETE
+        async fn model_data(
+            &self,
+            model_ids: &[StreamId],
+            method: Method,
+            prefetch: bool,
+        ) -> Result<()> {
+            self.with_admin_code(prefetch, |code| {
+                let method = method.clone();
+                async move {
+                    let req = if method == Method::DELETE {
+                        self.cli
+                            .create_unindex_models_request(model_ids, &code)
+                            .await?
+                    } else {
+                        self.cli
+                            .create_index_models_request(model_ids, &code)
+                            .await?
+                    };
+                    let resp = self
+                        .send(
+                            self.remote
+                                .request(method, self.url_for_path(self.cli.index_endpoint())?)
+                                .json(&req),
+                            Idempotency::Idempotent,
+                        )
+                        .await?;
+                    let status = resp.status();
+                    if status.is_success() {
+                        Ok(())
+                    } else {
+                        Err(Error::Http {
+                            status: status.as_u16(),
+                            body: resp.text().await?,
+                        })
+                    }
+                }
+            })
+            .await
+        }
+
         /// List indexed models on the remote ceramic
-        pub async fn list_indexed_models(&self) -> anyhow::Result<api::ListIndexedModelsResponse> {
-            let resp: api::AdminCodeResponse = self
-                .remote
-                .get(self.url_for_path(self.cli.admin_code_endpoint())?)
-                .send()
-                .await?
-                .json()
+        pub async fn list_indexed_models(&self) -> Result<api::ListIndexedModelsResponse> {
+            self.with_admin_code(false, |code| async move {
+                let req = self.cli.create_list_indexed_models_request(&code).await?;
+                let resp = self
+                    .send(
+                        self.remote
+                            .get(self.url_for_path(self.cli.models_endpoint())?)
+                            .header(
+                                reqwest::header::AUTHORIZATION,
+                                format!("Basic {}", req.jws()),
+                            ),
+                        Idempotency::Idempotent,
+                    )
+                    .await?;
+                decode(admin_response(resp).await?, "list_indexed_models").await
+            })
+            .await
+        }
+
+        /// Plan the deployment of a set of models, identified by the deterministic id of their
+        /// genesis commit: the models that are not indexed yet must be created and indexed.
+        ///
+        /// A node cannot tell quickly whether a stream it has not indexed exists, as it searches
+        /// the network for its genesis until a timeout. Since posting a genesis is idempotent,
+        /// unindexed models are created again instead, which leaves existing ones unchanged.
+        /// Models to create are validated against the interfaces they implement, which may be
+        /// part of the same set.
+        ///
+        /// Model ids are only stable for a client signing with a did:key, so clients signing
+        /// with a did session are rejected.
+        pub async fn plan_models(&self, models: &[ModelDefinition]) -> Result<ApplyPlan> {
+            if self.cli.capability.is_some() {
+                return Err(Error::SessionUnsupported("plan_models"));
+            }
+            let indexed = self.list_indexed_models().await?.models;
+            let mut planned = Vec::with_capacity(models.len());
+            for model in models {
+                let (req, stream_id) = self.cli.create_model_request(model).await?;
+                let create = !indexed.contains(&stream_id);
+                planned.push(PlannedModel {
+                    name: model.name().to_string(),
+                    create,
+                    index: create,
+                    request: create.then_some(req),
+                    stream_id,
+                });
+            }
+            for (model, plan) in models.iter().zip(&planned) {
+                if !plan.create {
+                    continue;
+                }
+                let mut interfaces = Vec::with_capacity(model.implements().len());
+                for interface in model.implements() {
+                    match planned.iter().position(|p| &p.stream_id == interface) {
+                        Some(pos) => interfaces.push(models[pos].clone()),
+                        None => interfaces.push(self.get_model(interface).await?),
+                    }
+                }
+                model.validate(&interfaces)?;
+            }
+            Ok(ApplyPlan { models: planned })
+        }
+
+        /// Execute a plan, creating the models to create in the order of the plan, then indexing
+        /// the models that are not indexed yet. Returns the outcome of indexing each model.
+        pub async fn execute_plan(&self, plan: &ApplyPlan) -> Result<Vec<api::ModelIndexOutcome>> {
+            for model in plan.to_create() {
+                if let Some(req) = &model.request {
+                    self.post_genesis(req, &model.stream_id, "execute_plan")
+                        .await?;
+                }
+            }
+            let to_index: Vec<_> = plan.to_index().map(|m| m.stream_id.clone()).collect();
+            if to_index.is_empty() {
+                return Ok(Vec::new());
+            }
+            self.index_models(&to_index).await
+        }
+
+        /// Plan the deployment of a set of models and, unless `dry_run` is set, execute the plan.
+        /// Returns the plan, failing if any model could not be created or indexed.
+        pub async fn apply_models(
+            &self,
+            models: &[ModelDefinition],
+            dry_run: bool,
+        ) -> Result<ApplyPlan> {
+            let plan = self.plan_models(models).await?;
+            if !dry_run {
+                for outcome in self.execute_plan(&plan).await? {
+                    outcome.result?;
+                }
+            }
+            Ok(plan)
+        }
+
+        /// Create an instance of a model that allows a single instance on the remote ceramic
+        pub async fn create_single_instance(&self, model_id: &StreamId) -> Result<StreamId> {
+            let (req, stream_id) = self.cli.create_single_instance_request(model_id).await?;
+            let resp = self
+                .post_genesis(&req, &stream_id, "create_single_instance")
                 .await?;
-            let req = self
+            Ok(resp.stream_id)
+        }
+
+        /// Load the single instance of a model controlled by this client, creating it if needed,
+        /// and replace its content with `content` if it differs
+        pub async fn get_or_create_single_instance<T: Serialize>(
+            &self,
+            model_id: &StreamId,
+            content: T,
+        ) -> Result<api::StreamsResponse> {
+            let (req, stream_id) = self.cli.create_single_instance_request(model_id).await?;
+            self.get_or_create_deterministic(
+                model_id,
+                &req,
+                &stream_id,
+                content,
+                "get_or_create_single_instance",
+            )
+            .await
+        }
+
+        /// Load the instance of a model with a set account relation controlled by this client and
+        /// identified by the values of its unique fields, creating it if needed, and replace its
+        /// content with `content` if it differs. The model is loaded to check there is a value
+        /// for each of its unique fields.
+        pub async fn get_or_create_set_instance<T: Serialize>(
+            &self,
+            model_id: &StreamId,
+            unique: &[String],
+            content: T,
+        ) -> Result<api::StreamsResponse> {
+            self.get_model(model_id).await?.validate_unique(unique)?;
+            let (req, stream_id) = self
                 .cli
-                .create_list_indexed_models_request(&resp.code)
-                .await?;
-            let resp = self
-                .remote
-                .get(self.url_for_path(self.cli.models_endpoint())?)
-                .header(
-                    reqwest::header::AUTHORIZATION,
-                    format!("Basic {}", req.jws()),
-                )
-                .send()
-                .await?
-                .json()
+                .create_set_instance_request(model_id, unique)
                 .await?;
-            Ok(resp)
+            self.get_or_create_deterministic(
+                model_id,
+                &req,
+                &stream_id,
+                content,
+                "get_or_create_set_instance",
+            )
+            .await
         }
 
-        /// Create an instance of a model that allows a single instance on the remote ceramic
-        pub async fn create_single_instance(
+        /// Update the instance of a model with a set account relation controlled by this client
+        /// and identified by the values of its unique fields
+        pub async fn update_set_instance(
+            &self,
+            model_id: &StreamId,
+            unique: &[String],
+            patch: json_patch::Patch,
+        ) -> Result<api::StreamsResponse> {
+            self.get_model(model_id).await?.validate_unique(unique)?;
+            let stream_id = self.cli.set_instance_stream_id(model_id, unique)?;
+            self.update(model_id, &stream_id, patch).await
+        }
+
+        async fn get_or_create_deterministic<R: Serialize, T: Serialize>(
             &self,
             model_id: &StreamId,
-        ) -> anyhow::Result<StreamId> {
-            let req = self.cli.create_single_instance_request(model_id).await?;
-            let resp: api::StreamsResponseOrError = self
-                .remote
-                .post(self.url_for_path(self.cli.streams_endpoint())?)
-                .json(&req)
-                .send()
-                .await?
-                .json()
+            req: &api::CreateRequest<R>,
+            stream_id: &StreamId,
+            content: T,
+            context: &str,
+        ) -> Result<api::StreamsResponse> {
+            let resp = self.post_genesis(req, stream_id, context).await?;
+            let content = serde_json::to_value(content)?;
+            if resp.state.as_ref().map(|st| &st.content) == Some(&content) {
+                return Ok(resp);
+            }
+            let req = self
+                .cli
+                .create_replace_request(model_id, &resp, content)
                 .await?;
-            Ok(resp.resolve("create_single_instance")?.stream_id)
+            self.post_commit(&req, context).await
         }
 
         /// Create an instance of a model allowing multiple instances on a remote ceramic
@@ -416,41 +1345,113 @@ pub mod remote {
             &self,
             model_id: &StreamId,
             instance: T,
-        ) -> anyhow::Result<StreamId> {
-            let req = self
+        ) -> Result<StreamId> {
+            let (req, stream_id) = self
                 .cli
                 .create_list_instance_request(model_id, instance)
                 .await?;
-            let resp: api::StreamsResponseOrError = self
-                .remote
-                .post(self.url_for_path(self.cli.streams_endpoint())?)
-                .json(&req)
-                .send()
-                .await?
-                .json()
+            let resp = self
+                .post_genesis(&req, &stream_id, "create_list_instance")
                 .await?;
-            let resp = resp.resolve("create_list_instance")?;
             Ok(resp.stream_id)
         }
 
+        async fn post_commit(
+            &self,
+            req: &api::UpdateRequest,
+            context: &str,
+        ) -> Result<api::StreamsResponse> {
+            let resp = self
+                .send(
+                    self.remote
+                        .post(self.url_for_path(self.cli.commits_endpoint())?)
+                        .json(req),
+                    Idempotency::NonIdempotent,
+                )
+                .await?;
+            let resp: api::StreamsResponseOrError = decode(resp, context).await?;
+            resp.resolve(context)
+        }
+
+        /// Post a commit based on the tip of a loaded stream, failing with a conflict if the node
+        /// does not take the commit as its new tip, or rejects it after the tip changed
+        async fn post_commit_at_tip(
+            &self,
+            req: &api::UpdateRequest,
+            loaded: &api::StreamsResponse,
+            commit: &Cid,
+            context: &str,
+        ) -> Result<api::StreamsResponse> {
+            let expected = loaded.tip()?;
+            let conflict = |actual| Error::Conflict {
+                stream_id: Box::new(loaded.stream_id.clone()),
+                expected: Box::new(expected),
+                actual: Box::new(actual),
+            };
+            match self.post_commit(req, context).await {
+                Ok(resp) => {
+                    let actual = resp.tip()?;
+                    if &actual != commit {
+                        return Err(conflict(actual));
+                    }
+                    Ok(resp)
+                }
+                Err(e) if e.is_rejected_request() => {
+                    let actual = self.get(&loaded.stream_id).await?.tip()?;
+                    if actual != expected {
+                        return Err(conflict(actual));
+                    }
+                    Err(e)
+                }
+                Err(e) => Err(e),
+            }
+        }
+
+        /// Get a stream, failing with a conflict if its tip is not the expected one
+        async fn get_at_tip(
+            &self,
+            stream_id: &StreamId,
+            expected: &Cid,
+        ) -> Result<api::StreamsResponse> {
+            let resp = self.get(stream_id).await?;
+            let actual = resp.tip()?;
+            if &actual != expected {
+                return Err(Error::Conflict {
+                    stream_id: Box::new(stream_id.clone()),
+                    expected: Box::new(*expected),
+                    actual: Box::new(actual),
+                });
+            }
+            Ok(resp)
+        }
+
         /// Update an instance that was previously created
         pub async fn update(
             &self,
             model: &StreamId,
             stream_id: &StreamId,
             patch: json_patch::Patch,
-        ) -> anyhow::Result<api::StreamsResponse> {
+        ) -> Result<api::StreamsResponse> {
             let resp = self.get(stream_id).await?;
             let req = self.cli.create_update_request(model, &resp, patch).await?;
-            let resp: api::StreamsResponseOrError = self
-                .remote
-                .post(self.url_for_path(self.cli.commits_endpoint())?)
-                .json(&req)
-                .send()
-                .await?
-                .json()
+            self.post_commit(&req, "update").await
+        }
+
+        /// Update an instance, failing with `Error::Conflict` if its tip is no longer `expected`
+        pub async fn update_at_tip(
+            &self,
+            model: &StreamId,
+            stream_id: &StreamId,
+            expected: &Cid,
+            patch: json_patch::Patch,
+        ) -> Result<api::StreamsResponse> {
+            let resp = self.get_at_tip(stream_id, expected).await?;
+            let (req, commit) = self
+                .cli
+                .update_commit(model, &resp, patch, &Default::default())
                 .await?;
-            resp.resolve("update")
+            self.post_commit_at_tip(&req, &resp, &commit, "update")
+                .await
         }
 
         /// Replace an instance that was previously created
@@ -459,60 +1460,313 @@ pub mod remote {
             model: &StreamId,
             stream_id: &StreamId,
             data: T,
-        ) -> anyhow::Result<api::StreamsResponse> {
+        ) -> Result<api::StreamsResponse> {
             let resp = self.get(stream_id).await?;
             let req = self.cli.create_replace_request(model, &resp, data).await?;
-            let resp: api::StreamsResponseOrError = self
-                .remote
-                .post(self.url_for_path(self.cli.commits_endpoint())?)
-                .json(&req)
-                .send()
-                .await?
-                .json()
+            self.post_commit(&req, "replace").await
+        }
+
+        /// Replace an instance, failing with `Error::Conflict` if its tip is no longer `expected`
+        pub async fn replace_at_tip<T: Serialize>(
+            &self,
+            model: &StreamId,
+            stream_id: &StreamId,
+            expected: &Cid,
+            data: T,
+        ) -> Result<api::StreamsResponse> {
+            let resp = self.get_at_tip(stream_id, expected).await?;
+            self.replace_loaded(model, &resp, data).await
+        }
+
+        /// Replace a loaded instance, failing with `Error::Conflict` if its tip changed since it
+        /// was loaded
+        async fn replace_loaded<T: Serialize>(
+            &self,
+            model: &StreamId,
+            loaded: &api::StreamsResponse,
+            data: T,
+        ) -> Result<api::StreamsResponse> {
+            let (req, commit) = self
+                .cli
+                .replace_commit(model, loaded, data, &Default::default())
                 .await?;
-            resp.resolve("replace")
+            self.post_commit_at_tip(&req, loaded, &commit, "replace")
+                .await
+        }
+
+        /// Read the content of an instance, modify it and write it back, retrying the
+        /// modification up to `max_attempts` times if the instance is updated concurrently
+        pub async fn read_modify_write<T, F>(
+            &self,
+            model: &StreamId,
+            stream_id: &StreamId,
+            max_attempts: usize,
+            mut modify: F,
+        ) -> Result<api::StreamsResponse>
+        where
+            T: DeserializeOwned + Serialize,
+            F: FnMut(T) -> T,
+        {
+            let mut attempt = 1;
+            loop {
+                let resp = self.get(stream_id).await?;
+                let content = match &resp.state {
+                    Some(st) => serde_json::from_value(st.content.clone())?,
+                    None => return Err(Error::NoCommits(stream_id.clone())),
+                };
+                match self.replace_loaded(model, &resp, modify(content)).await {
+                    Err(Error::Conflict { .. }) if attempt < max_attempts => attempt += 1,
+                    res => return res,
+                }
+            }
         }
 
         /// Get an instance of model
-        pub async fn get(&self, stream_id: &StreamId) -> anyhow::Result<api::StreamsResponse> {
+        pub async fn get(&self, stream_id: &StreamId) -> Result<api::StreamsResponse> {
             let endpoint = format!("{}/{}", self.cli.streams_endpoint(), stream_id);
             let endpoint = self.url_for_path(&endpoint)?;
-            let resp: api::StreamsResponse = self.remote.get(endpoint).send().await?.json().await?;
+            let resp = self
+                .send(self.remote.get(endpoint), Idempotency::Idempotent)
+                .await?;
+            let resp: api::StreamsResponseOrError = decode(resp, "get").await?;
+            let resp = resp.resolve("get")?;
+            self.verify_loaded(resp).await
+        }
+
+        /// Verify a loaded stream if verification is enabled, requiring the controller reported by
+        /// the node to match the one in its genesis
+        async fn verify_loaded(&self, resp: api::StreamsResponse) -> Result<api::StreamsResponse> {
+            if self.verify {
+                let controller = resp
+                    .state
+                    .as_ref()
+                    .and_then(|st| st.metadata.controllers.first());
+                let tip = resp.tip()?;
+                let commits = self.raw_commits(&resp.stream_id).await?;
+                verify::verify_commits_until(
+                    &resp.stream_id,
+                    controller.map(String::as_str),
+                    &commits,
+                    &tip,
+                )
+                .into_result()?;
+            }
             Ok(resp)
         }
 
+        /// Load many streams, at specific commits or along with linked streams, in a single
+        /// request
+        pub async fn multi_query(
+            &self,
+            queries: Vec<api::MultiQuery>,
+        ) -> Result<api::MultiQueryResponse> {
+            let req = self.cli.create_multi_query_request(queries).await?;
+            let resp = self
+                .send(
+                    self.remote
+                        .post(self.url_for_path(self.cli.multi_queries_endpoint())?)
+                        .json(&req),
+                    Idempotency::Idempotent,
+                )
+                .await?;
+            decode(resp, "multi_query").await
+        }
+
+        /// Get many streams in a single request, with the outcome of loading each stream. Streams
+        /// are verified as with `get` when verification is enabled, and repeated ids are loaded
+        /// once.
+        pub async fn get_many(
+            &self,
+            stream_ids: &[StreamId],
+        ) -> Result<HashMap<StreamId, Result<api::StreamsResponse>>> {
+            let mut seen = HashSet::with_capacity(stream_ids.len());
+            let stream_ids: Vec<_> = stream_ids.iter().filter(|id| seen.insert(*id)).collect();
+            let queries = stream_ids
+                .iter()
+                .map(|id| api::MultiQuery::new(id))
+                .collect();
+            let mut states = self.multi_query(queries).await?;
+            let mut resps = HashMap::with_capacity(stream_ids.len());
+            for stream_id in stream_ids {
+                let resp = match states.remove(&stream_id.to_string()) {
+                    Some(state) => {
+                        let resp = api::StreamsResponse {
+                            stream_id: stream_id.clone(),
+                            state: Some(state),
+                        };
+                        self.verify_loaded(resp).await
+                    }
+                    None => Err(Error::StreamNotFound(stream_id.clone())),
+                };
+                resps.insert(stream_id.clone(), resp);
+            }
+            Ok(resps)
+        }
+
+        /// Get many streams in a single request, with the content of each stream deserialized to a
+        /// serde compatible type
+        pub async fn get_many_as<T: DeserializeOwned>(
+            &self,
+            stream_ids: &[StreamId],
+        ) -> Result<HashMap<StreamId, Result<T>>> {
+            let resps = self.get_many(stream_ids).await?;
+            Ok(resps
+                .into_iter()
+                .map(|(stream_id, resp)| {
+                    let content = resp.and_then(|resp| {
+                        let st = resp
+                            .state
+                            .ok_or_else(|| Error::NoCommits(stream_id.clone()))?;
+                        Ok(serde_json::from_value(st.content)?)
+                    });
+                    (stream_id, content)
+                })
+                .collect())
+        }
+
+        /// Wait until the latest commit of a stream is anchored, polling with the backoff of the
+        /// retry policy. Fails if anchoring fails or does not finish within `timeout`.
+        pub async fn wait_for_anchor(
+            &self,
+            stream_id: &StreamId,
+            timeout: Duration,
+        ) -> Result<api::StreamsResponse> {
+            let deadline = Instant::now() + timeout;
+            let mut attempt = 0;
+            loop {
+                let resp = self.get(stream_id).await?;
+                match resp.anchor_status() {
+                    Some(api::StreamAnchorStatus::Anchored) => return Ok(resp),
+                    Some(api::StreamAnchorStatus::Failed) => {
+                        return Err(Error::AnchorFailed(stream_id.clone()))
+                    }
+                    _ => {}
+                }
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return Err(Error::AnchorTimeout(stream_id.clone()));
+                }
+                attempt += 1;
+                tokio::time::sleep(self.retry.backoff(attempt).min(remaining)).await;
+            }
+        }
+
+        /// Verify the signatures and log of a stream against the controller in its genesis
+        pub async fn verify(&self, stream_id: &StreamId) -> Result<Verification> {
+            let commits = self.raw_commits(stream_id).await?;
+            Ok(verify::verify_commits(stream_id, None, &commits))
+        }
+
+        async fn raw_commits(&self, stream_id: &StreamId) -> Result<Vec<verify::RawCommit>> {
+            let endpoint = format!("{}/{}", self.cli.commits_endpoint(), stream_id);
+            let endpoint = self.url_for_path(&endpoint)?;
+            let resp = self
+                .send(self.remote.get(endpoint), Idempotency::Idempotent)
+                .await?;
+            let resp: verify::RawCommits = decode(resp, "verify").await?;
+            Ok(resp.commits)
+        }
+
         /// Get the content of an instance of a model as a serde compatible type
-        pub async fn get_as<T: DeserializeOwned>(&self, stream_id: &StreamId) -> anyhow::Result<T> {
+        pub async fn get_as<T: DeserializeOwned>(&self, stream_id: &StreamId) -> Result<T> {
             let resp = self.get(stream_id).await?;
             if let Some(st) = resp.state {
                 let resp = serde_json::from_value(st.content)?;
                 Ok(resp)
             } else {
-                Err(anyhow::anyhow!("No commits for stream {}", stream_id))
+                Err(Error::NoCommits(stream_id.clone()))
             }
         }
 
+        /// Load the definition of a model, including its schema, relations and views
+        pub async fn get_model(&self, model_id: &StreamId) -> Result<ModelDefinition> {
+            Error::expect_model(model_id)?;
+            self.get_as(model_id).await
+        }
+
+        /// Get an instance of a model as of a specific commit. When verification is enabled, the
+        /// log up to that commit is verified as with `get`.
+        pub async fn get_at_commit(&self, commit_id: &CommitId) -> Result<api::StreamsResponse> {
+            let endpoint = format!(
+                "{}/{}",
+                self.cli.streams_endpoint(),
+                commit_id.to_multibase()?
+            );
+            let endpoint = self.url_for_path(&endpoint)?;
+            let resp = self
+                .send(self.remote.get(endpoint), Idempotency::Idempotent)
+                .await?;
+            let resp: api::StreamsResponseOrError = decode(resp, "get_at_commit").await?;
+            self.verify_loaded(resp.resolve("get_at_commit")?).await
+        }
+
+        /// Get an instance of a model as it was anchored at the given time. When verification is
+        /// enabled, the log up to the loaded commit is verified as with `get`.
+        pub async fn get_at_time(
+            &self,
+            stream_id: &StreamId,
+            at_time: SystemTime,
+        ) -> Result<api::StreamsResponse> {
+            let at_time = at_time
+                .duration_since(UNIX_EPOCH)
+                .map_err(|e| Error::Encoding(e.into()))?;
+            let endpoint = format!("{}/{}", self.cli.streams_endpoint(), stream_id);
+            let mut endpoint = self.url_for_path(&endpoint)?;
+            endpoint
+                .query_pairs_mut()
+                .append_pair("atTime", &at_time.as_secs().to_string());
+            let resp = self
+                .send(self.remote.get(endpoint), Idempotency::Idempotent)
+                .await?;
+            let resp: api::StreamsResponseOrError = decode(resp, "get_at_time").await?;
+            self.verify_loaded(resp.resolve("get_at_time")?).await
+        }
+
+        /// List all commits of a stream
+        pub async fn commits(&self, stream_id: &StreamId) -> Result<api::CommitsResponse> {
+            let endpoint = format!("{}/{}", self.cli.commits_endpoint(), stream_id);
+            let endpoint = self.url_for_path(&endpoint)?;
+            let resp = self
+                .send(self.remote.get(endpoint), Idempotency::Idempotent)
+                .await?;
+            decode(resp, "commits").await
+        }
+
         /// Query for documents, optionally matching a filter
         pub async fn query(
             &self,
             model_id: &StreamId,
             query: Option<FilterQuery>,
             pagination: Pagination,
-        ) -> anyhow::Result<api::QueryResponse> {
+        ) -> Result<api::QueryResponse> {
             let req = self
                 .cli
                 .create_query_request(model_id, query, pagination)
                 .await?;
             let endpoint = self.url_for_path(self.cli.collection_endpoint())?;
             let resp = self
-                .remote
-                .post(endpoint)
-                .json(&req)
-                .send()
-                .await?
-                .json()
+                .send(
+                    self.remote.post(endpoint).json(&req),
+                    Idempotency::Idempotent,
+                )
                 .await?;
-            Ok(resp)
+            let resp: serde_json::Value = decode(resp, "query").await?;
+            if self.verify {
+                // Verify the logs returned along with the documents, rather than fetching them
+                let raw: verify::RawQueryResponse = serde_json::from_value(resp.clone())?;
+                for edge in &raw.edges {
+                    let genesis = edge.node.log.first().ok_or_else(|| Error::Ceramic {
+                        context: "query".to_string(),
+                        message: "document without commits".to_string(),
+                    })?;
+                    let stream_id = StreamId {
+                        r#type: StreamIdType::ModelInstanceDocument,
+                        cid: Cid::from_str(&genesis.cid).map_err(|e| Error::Encoding(e.into()))?,
+                    };
+                    verify::verify_commits(&stream_id, None, &edge.node.log).into_result()?;
+                }
+            }
+            Ok(serde_json::from_value(resp)?)
         }
 
         /// Query for documents matching a filter, deserialized to a serde compatible type
@@ -521,60 +1775,183 @@ pub mod remote {
             model_id: &StreamId,
             query: Option<FilterQuery>,
             pagination: Pagination,
-        ) -> anyhow::Result<api::TypedQueryResponse<T>> {
+        ) -> Result<api::TypedQueryResponse<T>> {
             let resp = self.query(model_id, query, pagination).await?;
-            let try_docs: Result<Vec<_>, _> = resp
-                .edges
-                .into_iter()
-                .map(|edge| {
-                    serde_json::from_value(edge.node.content).map(|doc| api::TypedQueryDocument {
-                        document: doc,
-                        commits: edge.node.log,
-                    })
-                })
-                .collect();
+            let try_docs: Result<Vec<_>> = resp.edges.into_iter().map(typed_document).collect();
             Ok(api::TypedQueryResponse {
                 documents: try_docs?,
                 page_info: resp.page_info,
             })
         }
 
-        /// Check Ceramic node health
-        pub async fn healthcheck(&self) -> anyhow::Result<String> {
-            let req = self.cli.create_healthcheck_request().await?;
+        /// Query for documents, following cursors from page to page in the direction of the
+        /// pagination until all matching documents have been returned
+        pub fn query_stream(
+            &self,
+            model_id: &StreamId,
+            query: Option<FilterQuery>,
+            pagination: Pagination,
+        ) -> impl Stream<Item = Result<api::QueryEdge>> + '_ {
+            let model_id = model_id.clone();
+            stream::try_unfold(Some(pagination), move |pagination| {
+                let model_id = model_id.clone();
+                let query = query.clone();
+                async move {
+                    let pagination = match pagination {
+                        Some(pagination) => pagination,
+                        None => return Ok(None),
+                    };
+                    let resp = self.query(&model_id, query, pagination.clone()).await?;
+                    let next = if resp.edges.is_empty() {
+                        None
+                    } else {
+                        pagination.next_page(&resp.page_info)
+                    };
+                    Ok::<_, Error>(Some((resp.edges, next)))
+                }
+            })
+            .map_ok(|edges| stream::iter(edges.into_iter().map(Ok)))
+            .try_flatten()
+        }
+
+        /// Query for documents across all pages, deserialized to a serde compatible type
+        pub fn query_stream_as<'a, T: DeserializeOwned + 'a>(
+            &'a self,
+            model_id: &StreamId,
+            query: Option<FilterQuery>,
+            pagination: Pagination,
+        ) -> impl Stream<Item = Result<api::TypedQueryDocument<T>>> + 'a {
+            self.query_stream(model_id, query, pagination)
+                .and_then(|edge| future::ready(typed_document(edge)))
+        }
+
+        /// Collect up to `limit` documents across all pages
+        pub async fn query_all(
+            &self,
+            model_id: &StreamId,
+            query: Option<FilterQuery>,
+            pagination: Pagination,
+            limit: usize,
+        ) -> Result<Vec<api::QueryEdge>> {
+            self.query_stream(model_id, query, pagination)
+                .take(limit)
+                .try_collect()
+                .await
+        }
+
+        /// Collect up to `limit` documents across all pages, deserialized to a serde compatible
+        /// type
+        pub async fn query_all_as<T: DeserializeOwned>(
+            &self,
+            model_id: &StreamId,
+            query: Option<FilterQuery>,
+            pagination: Pagination,
+            limit: usize,
+        ) -> Result<Vec<api::TypedQueryDocument<T>>> {
+            self.query_stream_as(model_id, query, pagination)
+                .take(limit)
+                .try_collect()
+                .await
+        }
+
+        /// Pin a stream on the remote ceramic, so the node keeps its content. If `force` is set,
+        /// the stream is pinned again even if it is already pinned.
+        pub async fn pin(&self, stream_id: &StreamId, force: bool) -> Result<api::PinResponse> {
+            let req = self.cli.create_pin_request(force).await?;
+            self.pin_request(Method::POST, stream_id, &req, "pin").await
+        }
+
+        /// Unpin a stream on the remote ceramic
+        pub async fn unpin(&self, stream_id: &StreamId) -> Result<api::PinResponse> {
+            let req = self.cli.create_unpin_request().await?;
+            self.pin_request(Method::DELETE, stream_id, &req, "unpin")
+                .await
+        }
+
+        async fn pin_request(
+            &self,
+            method: Method,
+            stream_id: &StreamId,
+            req: &api::PinRequest,
+            context: &str,
+        ) -> Result<api::PinResponse> {
+            let endpoint = format!("{}/{}", self.cli.pins_endpoint(), stream_id);
             let resp = self
-                .remote
-                .get(self.url_for_path(self.cli.healthcheck_endpoint())?)
-                .json(&req)
-                .send()
-                .await?
-                .text()
+                .send(
+                    self.remote
+                        .request(method, self.url_for_path(&endpoint)?)
+                        .json(req),
+                    Idempotency::Idempotent,
+                )
                 .await?;
-            Ok(resp)
+            decode(resp, context).await
         }
 
-        /// Get the node status
-        pub async fn node_status(&self) -> anyhow::Result<api::NodeStatusResponse> {
-            let resp: api::AdminCodeResponse = self
-                .remote
-                .get(self.url_for_path(self.cli.admin_code_endpoint())?)
-                .send()
-                .await?
-                .json()
+        /// List the streams pinned on the remote ceramic. The response can be iterated over to
+        /// get the ids of the pinned streams.
+        pub async fn list_pins(&self) -> Result<api::ListPinsResponse> {
+            self.list_pins_at(self.cli.pins_endpoint()).await
+        }
+
+        /// Whether a stream is pinned on the remote ceramic
+        pub async fn is_pinned(&self, stream_id: &StreamId) -> Result<bool> {
+            let endpoint = format!("{}/{}", self.cli.pins_endpoint(), stream_id);
+            let pins = self.list_pins_at(&endpoint).await?;
+            Ok(pins.iter().any(|id| id == stream_id))
+        }
+
+        async fn list_pins_at(&self, endpoint: &str) -> Result<api::ListPinsResponse> {
+            let req = self.cli.create_list_pins_request().await?;
+            let resp = self
+                .send(
+                    self.remote.get(self.url_for_path(endpoint)?).json(&req),
+                    Idempotency::Idempotent,
+                )
                 .await?;
-            let req = self.cli.create_node_status_request(&resp.code).await?;
+            decode(resp, "list_pins").await
+        }
+
+        /// Check Ceramic node health
+        pub async fn healthcheck(&self) -> Result<String> {
+            let req = self.cli.create_healthcheck_request().await?;
             let resp = self
-                .remote
-                .get(self.url_for_path(self.cli.node_status_endpoint())?)
-                .header(
-                    reqwest::header::AUTHORIZATION,
-                    format!("Basic {}", req.jws()),
+                .send(
+                    self.remote
+                        .get(self.url_for_path(self.cli.healthcheck_endpoint())?)
+                        .json(&req),
+                    Idempotency::Idempotent,
                 )
-                .send()
-                .await?
-                .json()
                 .await?;
-            Ok(resp)
+            let status = resp.status();
+            let body = resp.text().await?;
+            if status.is_success() {
+                Ok(body)
+            } else {
+                Err(Error::Http {
+                    status: status.as_u16(),
+                    body,
+                })
+            }
+        }
+
+        /// Get the node status
+        pub async fn node_status(&self) -> Result<api::NodeStatusResponse> {
+            self.with_admin_code(false, |code| async move {
+                let req = self.cli.create_node_status_request(&code).await?;
+                let resp = self
+                    .send(
+                        self.remote
+                            .get(self.url_for_path(self.cli.node_status_endpoint())?)
+                            .header(
+                                reqwest::header::AUTHORIZATION,
+                                format!("Basic {}", req.jws()),
+                            ),
+                        Idempotency::Idempotent,
+                    )
+                    .await?;
+                decode(admin_response(resp).await?, "node_status").await
+            })
+            .await
         }
     }
 }
@@ -591,6 +1968,7 @@ pub mod tests {
     use schemars::JsonSchema;
     use serde::{Deserialize, Serialize};
     use std::collections::HashMap;
+    use std::str::FromStr;
     use std::time::Duration;
 
     // See https://github.com/ajv-validator/ajv-formats for information on valid formats
@@ -606,6 +1984,8 @@ pub mod tests {
 
     impl GetRootSchema for Ball {}
 
+    const ANCHOR_TIMEOUT: Duration = Duration::from_secs(60);
+
     pub fn ceramic_url() -> url::Url {
         let u =
             std::env::var("CERAMIC_URL").unwrap_or_else(|_| "http://localhost:7007".to_string());
@@ -629,6 +2009,61 @@ pub mod tests {
         cli.create_model(&model).await.unwrap()
     }
 
+    #[tokio::test]
+    async fn should_override_family_per_request() {
+        let cli = CeramicHttpClient::new(crate::session::tests::signer().await).with_family("prod");
+        let model = ModelDefinition::new::<Ball>("TestBall", ModelAccountRelation::List).unwrap();
+        let (req, model) = cli.create_model_request(&model).await.unwrap();
+        assert_eq!(req.block.header.family, "prod");
+        assert!(req.opts.is_none());
+
+        let opts = RequestOpts {
+            family: Some("staging".to_string()),
+            ..Default::default()
+        };
+        let (req, _) = cli
+            .create_list_instance_request_with_opts(&model, serde_json::json!({"red": 1}), &opts)
+            .await
+            .unwrap();
+        assert_eq!(req.block.header.family, "staging");
+        assert!(req.opts.is_none());
+        let opts = RequestOpts {
+            write: api::WriteOpts {
+                anchor: Some(true),
+                publish: None,
+            },
+            ..opts
+        };
+        let (req, _) = cli
+            .create_single_instance_request_with_opts(&model, &opts)
+            .await
+            .unwrap();
+        assert_eq!(req.block.header.family, "staging");
+        assert_eq!(
+            serde_json::to_value(&req.opts).unwrap(),
+            serde_json::json!({"anchor": true})
+        );
+    }
+
+    #[tokio::test]
+    async fn should_derive_set_instance_stream_id() {
+        // Computed independently of this crate by hashing the hand encoded dag-cbor genesis
+        // {data: null, header: {sep: "model", model, unique: ["1", "a"], controllers: [did]}},
+        // not by a ceramic node
+        let model =
+            StreamId::from_str("kjzl6hvfrbw6c8apa5yce6ah3fsz9sgrh6upniy0tz8z76gdm169ds3tf8c051t")
+                .unwrap();
+        let cli = CeramicHttpClient::new(crate::session::tests::signer().await);
+        let unique = ["1".to_string(), "a".to_string()];
+        assert_eq!(
+            cli.set_instance_stream_id(&model, &unique)
+                .unwrap()
+                .to_string(),
+            "k2t6wzhkhabz6jx9gz0ly7xkw3xjvqpdwrq8k29g6ij1bckphtfddxkqzh33ld"
+        );
+        assert!(cli.set_instance_stream_id(&model, &[]).is_err());
+    }
+
     #[tokio::test]
     async fn should_create_model() {
         let ceramic = CeramicRemoteHttpClient::new(signer().await, ceramic_url());
@@ -636,16 +2071,35 @@ pub mod tests {
         ceramic.create_model(&model).await.unwrap();
     }
 
-    // #[tokio::test]
-    // async fn should_create_single_instance() {
-    //     let ceramic = CeramicRemoteHttpClient::new(did(), &did_private_key(), ceramic_url());
-    //     let model = create_model(&ceramic).await;
-    //     ceramic.create_single_instance(&model).await.unwrap();
-    // }
+    #[tokio::test]
+    async fn should_get_or_create_single_instance() {
+        let ceramic = CeramicRemoteHttpClient::new(signer().await, ceramic_url());
+        let model = ModelDefinition::new::<Ball>("TestBall", ModelAccountRelation::Single).unwrap();
+        let model = ceramic.create_model(&model).await.unwrap();
+        let ball = Ball {
+            creator: ceramic.client().signer().id().id.clone(),
+            radius: 1,
+            red: 2,
+            green: 3,
+            blue: 4,
+        };
+        let created = ceramic
+            .get_or_create_single_instance(&model, &ball)
+            .await
+            .unwrap();
+        let ball = Ball { red: 3, ..ball };
+        let updated = ceramic
+            .get_or_create_single_instance(&model, &ball)
+            .await
+            .unwrap();
+        assert_eq!(created.stream_id, updated.stream_id);
+        let loaded: Ball = ceramic.get_as(&updated.stream_id).await.unwrap();
+        assert_eq!(loaded, ball);
+    }
 
     #[tokio::test]
     async fn should_create_and_update_list() {
-        let ceramic = CeramicRemoteHttpClient::new(signer().await, ceramic_url());
+        let ceramic = CeramicRemoteHttpClient::new(signer().await, ceramic_url()).with_anchor(true);
         let model = create_model(&ceramic).await;
         let stream_id = ceramic
             .create_list_instance(
@@ -661,8 +2115,10 @@ pub mod tests {
             .await
             .unwrap();
 
-        //give anchor time to complete
-        tokio::time::sleep(Duration::from_secs(1)).await;
+        ceramic
+            .wait_for_anchor(&stream_id, ANCHOR_TIMEOUT)
+            .await
+            .unwrap();
 
         let patch = json_patch::Patch(vec![json_patch::PatchOperation::Replace(
             ReplaceOperation {
@@ -675,8 +2131,10 @@ pub mod tests {
         let post_resp: Ball = serde_json::from_value(post_resp.state.unwrap().content).unwrap();
         assert_eq!(post_resp.red, 5);
 
-        //give anchor time to complete
-        tokio::time::sleep(Duration::from_secs(1)).await;
+        ceramic
+            .wait_for_anchor(&stream_id, ANCHOR_TIMEOUT)
+            .await
+            .unwrap();
 
         let patch = json_patch::Patch(vec![json_patch::PatchOperation::Replace(
             ReplaceOperation {
@@ -689,8 +2147,10 @@ pub mod tests {
         let post_resp: Ball = serde_json::from_value(post_resp.state.unwrap().content).unwrap();
         assert_eq!(post_resp.blue, 8);
 
-        //give anchor time to complete
-        tokio::time::sleep(Duration::from_secs(1)).await;
+        ceramic
+            .wait_for_anchor(&stream_id, ANCHOR_TIMEOUT)
+            .await
+            .unwrap();
 
         let get_resp: Ball = ceramic.get_as(&stream_id).await.unwrap();
         assert_eq!(get_resp.red, 5);
@@ -700,7 +2160,7 @@ pub mod tests {
 
     #[tokio::test]
     async fn should_create_and_replace_list() {
-        let ceramic = CeramicRemoteHttpClient::new(signer().await, ceramic_url());
+        let ceramic = CeramicRemoteHttpClient::new(signer().await, ceramic_url()).with_anchor(true);
         let model = create_model(&ceramic).await;
         let stream_id = ceramic
             .create_list_instance(
@@ -716,8 +2176,10 @@ pub mod tests {
             .await
             .unwrap();
 
-        //give anchor time to complete
-        tokio::time::sleep(Duration::from_secs(1)).await;
+        ceramic
+            .wait_for_anchor(&stream_id, ANCHOR_TIMEOUT)
+            .await
+            .unwrap();
 
         let replace = Ball {
             creator: ceramic.client().signer().id().id.clone(),
@@ -732,8 +2194,10 @@ pub mod tests {
         let post_resp: Ball = serde_json::from_value(post_resp.state.unwrap().content).unwrap();
         assert_eq!(post_resp, replace);
 
-        //give anchor time to complete
-        tokio::time::sleep(Duration::from_secs(1)).await;
+        ceramic
+            .wait_for_anchor(&stream_id, ANCHOR_TIMEOUT)
+            .await
+            .unwrap();
 
         let replace = Ball {
             creator: ceramic.client().signer().id().id.clone(),
@@ -747,8 +2211,10 @@ pub mod tests {
         let post_resp: Ball = serde_json::from_value(post_resp.state.unwrap().content).unwrap();
         assert_eq!(post_resp, replace);
 
-        //give anchor time to complete
-        tokio::time::sleep(Duration::from_secs(1)).await;
+        ceramic
+            .wait_for_anchor(&stream_id, ANCHOR_TIMEOUT)
+            .await
+            .unwrap();
 
         let get_resp: Ball = ceramic.get_as(&stream_id).await.unwrap();
         assert_eq!(get_resp, post_resp);
@@ -796,4 +2262,314 @@ pub mod tests {
             .unwrap();
         assert_eq!(res.edges.len(), 1);
     }
+
+    #[tokio::test]
+    async fn should_query_all_pages() {
+        let ceramic = CeramicRemoteHttpClient::new(signer().await, ceramic_url());
+        let model = create_model(&ceramic).await;
+        ceramic.index_model(&model).await.unwrap();
+        for radius in 0..3 {
+            ceramic
+                .create_list_instance(
+                    &model,
+                    &Ball {
+                        creator: ceramic.client().signer().id().id.clone(),
+                        radius,
+                        red: 2,
+                        green: 3,
+                        blue: 4,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+
+        let pagination = Pagination::First {
+            first: 1,
+            after: None,
+        };
+        let res: Vec<api::TypedQueryDocument<Ball>> = ceramic
+            .query_all_as(&model, None, pagination, 10)
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 3);
+
+        let pagination = Pagination::Last {
+            last: 1,
+            before: None,
+        };
+        let res = ceramic
+            .query_all(&model, None, pagination, 2)
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 2);
+    }
+
+    #[cfg(feature = "mock")]
+    mod mock {
+        use crate::api::StreamAnchorStatus;
+        use crate::mock::tests::{client, setup, Ball};
+        use crate::mock::MockCeramic;
+        use crate::remote::{CeramicRemoteHttpClient, RetryPolicy};
+        use crate::session::tests::signer;
+        use crate::{Error, ModelAccountRelation, ModelDefinition};
+        use serde_json::json;
+        use std::time::Duration;
+
+        #[tokio::test]
+        async fn should_detect_conflicting_updates() {
+            let (_mock, ceramic, model) = setup(ModelAccountRelation::List).await;
+            let stream_id = ceramic
+                .create_list_instance(&model, &Ball { radius: 1, red: 2 })
+                .await
+                .unwrap();
+            let genesis = ceramic.get(&stream_id).await.unwrap().tip().unwrap();
+            ceramic
+                .replace_at_tip(&model, &stream_id, &genesis, &Ball { radius: 1, red: 3 })
+                .await
+                .unwrap();
+            let err = ceramic
+                .replace_at_tip(&model, &stream_id, &genesis, &Ball { radius: 1, red: 4 })
+                .await
+                .unwrap_err();
+            assert!(matches!(err, crate::Error::Conflict { .. }));
+
+            ceramic
+                .read_modify_write(&model, &stream_id, 3, |ball: Ball| Ball {
+                    red: ball.red + 1,
+                    ..ball
+                })
+                .await
+                .unwrap();
+            let ball: Ball = ceramic.get_as(&stream_id).await.unwrap();
+            assert_eq!(ball, Ball { radius: 1, red: 4 });
+
+            // Concurrent writers read the same tip, so the node rejects the commit of the slower one
+            let increment = |ball: Ball| Ball {
+                red: ball.red + 1,
+                ..ball
+            };
+            let (first, second) = futures::join!(
+                ceramic.read_modify_write(&model, &stream_id, 1, increment),
+                ceramic.read_modify_write(&model, &stream_id, 1, increment),
+            );
+            let err = first.and(second).unwrap_err();
+            assert!(matches!(err, crate::Error::Conflict { .. }), "{}", err);
+            let (first, second) = futures::join!(
+                ceramic.read_modify_write(&model, &stream_id, 3, increment),
+                ceramic.read_modify_write(&model, &stream_id, 3, increment),
+            );
+            first.unwrap();
+            second.unwrap();
+            let ball: Ball = ceramic.get_as(&stream_id).await.unwrap();
+            assert_eq!(ball, Ball { radius: 1, red: 7 });
+        }
+
+        #[tokio::test]
+        async fn should_wait_for_anchor() {
+            let (_mock, ceramic, model) = setup(ModelAccountRelation::List).await;
+            let ceramic = ceramic.with_anchor(true);
+            let stream_id = ceramic
+                .create_list_instance(&model, &Ball { radius: 1, red: 2 })
+                .await
+                .unwrap();
+            let resp = ceramic.get(&stream_id).await.unwrap();
+            assert_eq!(resp.anchor_status(), Some(StreamAnchorStatus::Pending));
+            let resp = ceramic
+                .wait_for_anchor(&stream_id, Duration::from_secs(5))
+                .await
+                .unwrap();
+            assert_eq!(resp.anchor_status(), Some(StreamAnchorStatus::Anchored));
+        }
+
+        #[tokio::test]
+        async fn should_get_or_create_single_instance() {
+            let (_mock, ceramic, model) = setup(ModelAccountRelation::Single).await;
+            let created = ceramic
+                .get_or_create_single_instance(&model, &Ball { radius: 1, red: 2 })
+                .await
+                .unwrap();
+            let updated = ceramic
+                .get_or_create_single_instance(&model, &Ball { radius: 1, red: 3 })
+                .await
+                .unwrap();
+            assert_eq!(created.stream_id, updated.stream_id);
+            assert_eq!(
+                updated.stream_id,
+                ceramic.client().single_instance_stream_id(&model).unwrap()
+            );
+            let ball: Ball = ceramic.get_as(&updated.stream_id).await.unwrap();
+            assert_eq!(ball, Ball { radius: 1, red: 3 });
+        }
+
+        #[tokio::test]
+        async fn should_get_or_create_set_instance() {
+            let relation = ModelAccountRelation::Set {
+                fields: vec!["radius".to_string()],
+            };
+            let (_mock, ceramic, model) = setup(relation).await;
+            let small = vec!["1".to_string()];
+            let created = ceramic
+                .get_or_create_set_instance(&model, &small, &Ball { radius: 1, red: 2 })
+                .await
+                .unwrap();
+            assert_eq!(
+                created.stream_id,
+                ceramic
+                    .client()
+                    .set_instance_stream_id(&model, &small)
+                    .unwrap()
+            );
+            let large = ceramic
+                .get_or_create_set_instance(&model, &["2".to_string()], &Ball { radius: 2, red: 2 })
+                .await
+                .unwrap();
+            assert_ne!(created.stream_id, large.stream_id);
+            let err = ceramic
+                .get_or_create_set_instance(&model, &[], &Ball { radius: 2, red: 2 })
+                .await
+                .unwrap_err();
+            assert!(matches!(err, Error::InvalidModel { .. }));
+
+            let patch = json_patch::diff(&json!({"red": 2}), &json!({"red": 5}));
+            ceramic
+                .update_set_instance(&model, &small, patch)
+                .await
+                .unwrap();
+            let ball: Ball = ceramic.get_as(&created.stream_id).await.unwrap();
+            assert_eq!(ball, Ball { radius: 1, red: 5 });
+        }
+
+        #[tokio::test]
+        async fn should_apply_builder_options() {
+            use reqwest::header::{HeaderName, HeaderValue};
+            let mock = MockCeramic::start().await.unwrap();
+            let ceramic = CeramicRemoteHttpClient::builder(signer().await, mock.url())
+                .header(
+                    HeaderName::from_static("x-api-key"),
+                    HeaderValue::from_static("secret"),
+                )
+                .user_agent("ball-deployer")
+                .timeout(Duration::from_millis(200))
+                .retry_policy(RetryPolicy::none())
+                .build()
+                .unwrap();
+            assert_eq!(ceramic.healthcheck().await.unwrap(), "Alive!");
+            let model =
+                ModelDefinition::new::<Ball>("TestBall", ModelAccountRelation::List).unwrap();
+            ceramic.create_model(&model).await.unwrap();
+            let requests = mock.requests();
+            assert_eq!(requests.len(), 2);
+            for req in &requests {
+                assert_eq!(req.headers["x-api-key"], "secret");
+                assert_eq!(req.headers["user-agent"], "ball-deployer");
+            }
+
+            mock.delay_responses(Duration::from_secs(1));
+            match ceramic.healthcheck().await {
+                Err(Error::Transport(e)) => assert!(e.is_timeout()),
+                res => panic!("expected a timeout, got {:?}", res),
+            }
+        }
+
+        #[tokio::test]
+        async fn should_get_model() {
+            let mock = MockCeramic::start().await.unwrap();
+            let ceramic = client(&mock).await;
+            let mut model =
+                ModelDefinition::new::<Ball>("TestBall", ModelAccountRelation::List).unwrap();
+            model.with_description("A ball".to_string());
+            let model_id = ceramic.create_model(&model).await.unwrap();
+            let loaded = ceramic.get_model(&model_id).await.unwrap();
+            assert_eq!(loaded.name(), "TestBall");
+            assert_eq!(loaded.description(), Some("A ball"));
+            assert_eq!(
+                serde_json::to_value(&loaded).unwrap(),
+                serde_json::to_value(&model).unwrap()
+            );
+        }
+
+        #[tokio::test]
+        async fn should_index_and_unindex_batches() {
+            let (mock, ceramic, first) = setup(ModelAccountRelation::List).await;
+            let model =
+                ModelDefinition::new::<Ball>("OtherBall", ModelAccountRelation::List).unwrap();
+            let second = ceramic.create_model(&model).await.unwrap();
+            let document = ceramic
+                .create_list_instance(&first, &Ball { radius: 1, red: 2 })
+                .await
+                .unwrap();
+
+            let codes = mock.admin_code_requests();
+            let outcomes = ceramic
+                .index_models(&[first.clone(), document, second.clone()])
+                .await
+                .unwrap();
+            let accepted: Vec<_> = outcomes.iter().map(|o| o.is_ok()).collect();
+            assert_eq!(accepted, vec![true, false, true]);
+            assert_eq!(mock.indexed_models().len(), 2);
+            // One code for the rejected batch, then one for each model, fetched ahead within the
+            // batch but not after its last request
+            assert_eq!(mock.admin_code_requests(), codes + 4);
+
+            let outcomes = ceramic.unindex_models(&[first]).await.unwrap();
+            assert!(outcomes.iter().all(|o| o.is_ok()));
+            assert_eq!(mock.indexed_models(), vec![second.to_string()]);
+        }
+
+        #[tokio::test]
+        async fn should_get_many_streams() {
+            let (_mock, ceramic, model) = setup(ModelAccountRelation::List).await;
+            let first = ceramic
+                .create_list_instance(&model, &Ball { radius: 1, red: 2 })
+                .await
+                .unwrap();
+            let second = ceramic
+                .create_list_instance(&model, &Ball { radius: 3, red: 4 })
+                .await
+                .unwrap();
+            let missing = ceramic
+                .client()
+                .set_instance_stream_id(&model, &["none".to_string()])
+                .unwrap();
+
+            let balls = ceramic
+                .get_many_as::<Ball>(&[
+                    first.clone(),
+                    second.clone(),
+                    missing.clone(),
+                    first.clone(),
+                ])
+                .await
+                .unwrap();
+            assert_eq!(balls.len(), 3);
+            assert_eq!(balls[&first].as_ref().unwrap(), &Ball { radius: 1, red: 2 });
+            assert_eq!(
+                balls[&second].as_ref().unwrap(),
+                &Ball { radius: 3, red: 4 }
+            );
+            assert!(matches!(balls[&missing], Err(Error::StreamNotFound(_))));
+        }
+
+        #[tokio::test]
+        async fn should_pin_and_unpin_streams() {
+            let (_mock, ceramic, model) = setup(ModelAccountRelation::List).await;
+            let document = ceramic
+                .create_list_instance(&model, &Ball { radius: 1, red: 2 })
+                .await
+                .unwrap();
+
+            assert!(ceramic.pin(&model, false).await.unwrap().is_pinned);
+            assert!(ceramic.pin(&document, true).await.unwrap().is_pinned);
+            let pins = ceramic.list_pins().await.unwrap();
+            assert_eq!(
+                pins.pinned_stream_ids,
+                vec![model.clone(), document.clone()]
+            );
+
+            assert!(!ceramic.unpin(&model).await.unwrap().is_pinned);
+            assert!(!ceramic.is_pinned(&model).await.unwrap());
+            assert!(ceramic.is_pinned(&document).await.unwrap());
+        }
+    }
 }
@@ -0,0 +1,1010 @@
+use crate::FilterQuery;
+use base64::Engine;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use url::Url;
+
+const DAG_CBOR: u8 = 0x71;
+const DAG_JOSE: [u8; 2] = [0x85, 0x01];
+const STREAM_ID_CODEC: [u8; 2] = [0xce, 0x01];
+const MODEL: u64 = 2;
+const MODEL_INSTANCE_DOCUMENT: u64 = 3;
+
+/// A mock ceramic node serving the HTTP API from memory.
+///
+/// The mock accepts the requests produced by this crate, applying genesis and update commits to
+/// streams held in memory and evaluating collection queries against their content. Signatures
+/// are not verified, and requested anchors complete once the stream has been loaded while
+/// pending. The commits endpoint returns the jws and linked block of signed commits, so clients
+/// can verify them. Received requests are recorded, and failures or delays can be injected to
+/// exercise the retry and timeout handling of clients.
+pub struct MockCeramic {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockCeramic {
+    /// Start a mock node on a random local port. Must be called from within a tokio runtime.
+    pub async fn start() -> std::io::Result<Self> {
+        let state = Arc::new(Mutex::new(State::default()));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |req| handle(state.clone(), req))) }
+        });
+        let (tx, rx) = oneshot::channel::<()>();
+        let server = Server::from_tcp(listener)
+            .map_err(std::io::Error::other)?
+            .serve(make_service)
+            .with_graceful_shutdown(async {
+                rx.await.ok();
+            });
+        tokio::spawn(server);
+        Ok(Self {
+            addr,
+            state,
+            shutdown: Some(tx),
+        })
+    }
+
+    /// Url of the mock node, for use with `CeramicRemoteHttpClient::new`
+    pub fn url(&self) -> Url {
+        Url::parse(&format!("http://{}", self.addr)).expect("socket address is a valid url")
+    }
+
+    /// Ids of the streams created on the mock node, in creation order
+    pub fn stream_ids(&self) -> Vec<String> {
+        let state = self.state.lock().expect("mock state poisoned");
+        state.streams.iter().map(|s| s.id.clone()).collect()
+    }
+
+    /// Ids of the models indexed on the mock node
+    pub fn indexed_models(&self) -> Vec<String> {
+        let state = self.state.lock().expect("mock state poisoned");
+        state.indexed.clone()
+    }
+
+    /// Number of admin codes requested from the mock node
+    pub fn admin_code_requests(&self) -> usize {
+        let state = self.state.lock().expect("mock state poisoned");
+        state.code_requests
+    }
+
+    /// Expire all admin codes issued so far, as the node does a minute after issuing them
+    pub fn expire_admin_codes(&self) {
+        let mut state = self.state.lock().expect("mock state poisoned");
+        let codes = std::mem::take(&mut state.codes);
+        state.expired_codes.extend(codes);
+    }
+
+    /// Issue the next admin code already expired, as if the request using it was delayed
+    pub fn expire_next_admin_code(&self) {
+        let mut state = self.state.lock().expect("mock state poisoned");
+        state.expire_next_code = true;
+    }
+
+    /// Respond to the next `count` requests with a path starting with `path` with `status`,
+    /// without handling them
+    pub fn fail_next(&self, path: &str, status: u16, count: usize) {
+        let mut state = self.state.lock().expect("mock state poisoned");
+        let status = StatusCode::from_u16(status).expect("valid status code");
+        state.failures = Some((path.to_string(), status, count));
+    }
+
+    /// Wait for `delay` before responding to each request
+    pub fn delay_responses(&self, delay: Duration) {
+        let mut state = self.state.lock().expect("mock state poisoned");
+        state.delay = Some(delay);
+    }
+
+    /// Requests received by the mock node, in the order they were received
+    pub fn requests(&self) -> Vec<MockRequest> {
+        let state = self.state.lock().expect("mock state poisoned");
+        state.requests.clone()
+    }
+}
+
+/// A request received by the mock node
+#[derive(Clone, Debug)]
+pub struct MockRequest {
+    /// Method of the request
+    pub method: String,
+    /// Path of the request
+    pub path: String,
+    /// Headers of the request, with lowercase names
+    pub headers: HashMap<String, String>,
+}
+
+impl Drop for MockCeramic {
+    fn drop(&mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    streams: Vec<MockStream>,
+    indexed: Vec<String>,
+    pinned: Vec<String>,
+    codes: Vec<String>,
+    expired_codes: Vec<String>,
+    expire_next_code: bool,
+    code_requests: usize,
+    failures: Option<(String, StatusCode, usize)>,
+    delay: Option<Duration>,
+    requests: Vec<MockRequest>,
+}
+
+impl State {
+    /// Record a request, returning the delay before responding and the status of an injected
+    /// failure
+    fn receive(&mut self, req: &Request<Body>) -> (Option<Duration>, Option<StatusCode>) {
+        let headers = req
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        self.requests.push(MockRequest {
+            method: req.method().to_string(),
+            path: req.uri().path().to_string(),
+            headers,
+        });
+        let failure = match &mut self.failures {
+            Some((path, status, count)) if *count > 0 && req.uri().path().starts_with(&**path) => {
+                *count -= 1;
+                Some(*status)
+            }
+            _ => None,
+        };
+        (self.delay, failure)
+    }
+
+    fn stream(&self, id: &str) -> Option<&MockStream> {
+        self.streams.iter().find(|s| s.id == id)
+    }
+
+    fn stream_mut(&mut self, id: &str) -> Option<&mut MockStream> {
+        self.streams.iter_mut().find(|s| s.id == id)
+    }
+}
+
+struct MockStream {
+    id: String,
+    r#type: u64,
+    model: String,
+    controllers: Vec<String>,
+    content: Value,
+    log: Vec<MockCommit>,
+    anchor_status: &'static str,
+}
+
+struct MockCommit {
+    cid: String,
+    /// Value as returned by the commits endpoint, the jws, linked block and cacao block for signed
+    /// commits
+    value: Value,
+    content: Value,
+}
+
+impl MockStream {
+    fn state(&self) -> Value {
+        let mut state = self.state_at(self.log.len());
+        state["anchorStatus"] = json!(self.anchor_status);
+        state
+    }
+
+    /// State of the stream after the first `commits` commits were applied
+    fn state_at(&self, commits: usize) -> Value {
+        let log = &self.log[..commits];
+        // Genesis is type 0 and signed commits are type 1
+        let log_entry = |(i, c): (usize, &MockCommit)| json!({"cid": c.cid, "type": if i == 0 { 0 } else { 1 }});
+        json!({
+            "type": self.r#type,
+            "content": log.last().map(|c| &c.content).unwrap_or(&self.content),
+            "log": log.iter().enumerate().map(log_entry).collect::<Vec<_>>(),
+            "metadata": {
+                "controllers": self.controllers,
+                "model": self.model,
+            },
+        })
+    }
+
+    fn response(&self) -> Value {
+        json!({
+            "streamId": self.id,
+            "state": self.state(),
+        })
+    }
+
+    fn commits(&self) -> Vec<Value> {
+        self.log
+            .iter()
+            .map(|c| json!({"cid": c.cid, "value": c.value}))
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockHeader {
+    controllers: Vec<String>,
+    model: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Block {
+    header: BlockHeader,
+    #[serde(default)]
+    data: Option<Value>,
+    #[serde(default)]
+    jws: Option<Value>,
+    #[serde(default)]
+    linked_block: Option<String>,
+    #[serde(default)]
+    cacao_block: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+struct WriteOpts {
+    #[serde(default)]
+    anchor: bool,
+}
+
+impl WriteOpts {
+    fn anchor_status(&self) -> &'static str {
+        if self.anchor {
+            "PENDING"
+        } else {
+            "NOT_REQUESTED"
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CreateRequest {
+    r#type: Value,
+    genesis: Block,
+    #[serde(default)]
+    opts: WriteOpts,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateRequest {
+    commit: Block,
+    stream_id: String,
+    #[serde(default)]
+    opts: WriteOpts,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueryRequest {
+    model: String,
+    #[serde(default)]
+    query_filters: Option<FilterQuery>,
+    first: Option<usize>,
+    after: Option<String>,
+    last: Option<usize>,
+    before: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MultiQueryRequest {
+    queries: Vec<MultiQuery>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MultiQuery {
+    stream_id: String,
+}
+
+#[derive(Deserialize)]
+struct AdminRequest {
+    jws: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AdminPayload {
+    code: String,
+    request_body: Value,
+}
+
+type HandlerResult = Result<(StatusCode, Value), (StatusCode, String)>;
+
+async fn handle(
+    state: Arc<Mutex<State>>,
+    req: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let (delay, failure) = state.lock().expect("mock state poisoned").receive(&req);
+    if let Some(delay) = delay {
+        tokio::time::sleep(delay).await;
+    }
+    if let Some(status) = failure {
+        return Ok(error(status, "injected failure".to_string()));
+    }
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let auth = req
+        .headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim_start_matches("Basic ").to_string());
+    let body = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(body) => body,
+        Err(e) => return Ok(error(StatusCode::BAD_REQUEST, e.to_string())),
+    };
+    if method == Method::GET && path == "/api/v0/node/healthcheck" {
+        return Ok(Response::new(Body::from("Alive!")));
+    }
+    let mut state = state.lock().expect("mock state poisoned");
+    let result = match (method, path.as_str()) {
+        (Method::POST, "/api/v0/streams") => parse(&body).and_then(|r| create(&mut state, r)),
+        (Method::GET, p) if p.starts_with("/api/v0/streams/") => {
+            let id = &p["/api/v0/streams/".len()..];
+            let result = load(&state, id);
+            complete_anchor(&mut state, id);
+            result
+        }
+        (Method::POST, "/api/v0/commits") => parse(&body).and_then(|r| update(&mut state, r)),
+        (Method::GET, p) if p.starts_with("/api/v0/commits/") => {
+            commits(&state, &p["/api/v0/commits/".len()..])
+        }
+        (Method::POST, "/api/v0/collection") => parse(&body).and_then(|r| query(&state, r)),
+        (Method::POST, "/api/v0/multiqueries") => parse(&body).map(|r| multi_query(&state, r)),
+        (Method::GET, "/api/v0/admin/getCode") => Ok(admin_code(&mut state)),
+        (Method::POST, "/api/v0/admin/modelData") => {
+            parse(&body).and_then(|r: AdminRequest| index(&mut state, &r.jws))
+        }
+        (Method::DELETE, "/api/v0/admin/modelData") => {
+            parse(&body).and_then(|r: AdminRequest| unindex(&mut state, &r.jws))
+        }
+        (Method::GET, "/api/v0/admin/models") => models(&mut state, auth),
+        (Method::GET, "/api/v0/admin/status") => status(&mut state, auth),
+        (Method::GET, "/api/v0/pins") => Ok(pins(&state, None)),
+        (Method::GET, p) if p.starts_with("/api/v0/pins/") => {
+            Ok(pins(&state, Some(&p["/api/v0/pins/".len()..])))
+        }
+        (Method::POST, p) if p.starts_with("/api/v0/pins/") => {
+            pin(&mut state, &p["/api/v0/pins/".len()..])
+        }
+        (Method::DELETE, p) if p.starts_with("/api/v0/pins/") => {
+            Ok(unpin(&mut state, &p["/api/v0/pins/".len()..]))
+        }
+        (_, p) => Err((StatusCode::NOT_FOUND, format!("No route for {}", p))),
+    };
+    Ok(match result {
+        Ok((status, value)) => {
+            let mut resp = Response::new(Body::from(value.to_string()));
+            *resp.status_mut() = status;
+            resp.headers_mut().insert(
+                hyper::header::CONTENT_TYPE,
+                hyper::header::HeaderValue::from_static("application/json"),
+            );
+            resp
+        }
+        Err((status, message)) => error(status, message),
+    })
+}
+
+fn error(status: StatusCode, message: String) -> Response<Body> {
+    let mut resp = Response::new(Body::from(json!({ "error": message }).to_string()));
+    *resp.status_mut() = status;
+    resp
+}
+
+fn bad_request(message: impl ToString) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, message.to_string())
+}
+
+fn parse<T: serde::de::DeserializeOwned>(body: &[u8]) -> Result<T, (StatusCode, String)> {
+    serde_json::from_slice(body).map_err(bad_request)
+}
+
+fn create(state: &mut State, req: CreateRequest) -> HandlerResult {
+    let r#type = match &req.r#type {
+        Value::Number(n) => n.as_u64().unwrap_or_default(),
+        Value::String(s) if s == "Model" => MODEL,
+        Value::String(s) if s == "ModelInstanceDocument" => MODEL_INSTANCE_DOCUMENT,
+        other => return Err(bad_request(format!("Unknown stream type {}", other))),
+    };
+    let block = req.genesis;
+    let model = stream_id_from_base64(&block.header.model)?;
+    let (genesis_cid, content, value) = match (&block.jws, &block.linked_block) {
+        (Some(jws), Some(linked_block)) => {
+            let payload = decode_block(linked_block)?;
+            (
+                jws_cid(jws)?,
+                payload.get("data").cloned().unwrap_or(Value::Null),
+                signed_value(jws, linked_block, block.cacao_block.as_deref()),
+            )
+        }
+        _ => {
+            let data = block
+                .data
+                .ok_or_else(|| bad_request("Genesis has no data"))?;
+            let bytes = match data {
+                Value::String(s) => decode_base64(&s)?,
+                Value::Array(_) => serde_json::from_value(data).map_err(bad_request)?,
+                _ => return Err(bad_request("Invalid genesis data")),
+            };
+            (cid(&[DAG_CBOR], &bytes), Value::Null, decode_cbor(&bytes)?)
+        }
+    };
+    let id = stream_id(r#type, &genesis_cid);
+    if state.stream(&id).is_none() {
+        state.streams.push(MockStream {
+            id: id.clone(),
+            r#type,
+            model,
+            controllers: block.header.controllers,
+            content: content.clone(),
+            log: vec![MockCommit {
+                cid: cid_string(&genesis_cid),
+                value,
+                content,
+            }],
+            anchor_status: req.opts.anchor_status(),
+        });
+    }
+    let stream = state.stream(&id).expect("stream was inserted");
+    Ok((StatusCode::OK, stream.response()))
+}
+
+fn load(state: &State, id: &str) -> HandlerResult {
+    if let Some(stream) = state.stream(id) {
+        return Ok((StatusCode::OK, stream.response()));
+    }
+    let not_found = || (StatusCode::NOT_FOUND, format!("Stream {} not found", id));
+    let commit_id = decode_base36(id).ok_or_else(not_found)?;
+    for stream in &state.streams {
+        let stream_id = decode_base36(&stream.id).expect("stream ids are base36");
+        let commit = match commit_id.strip_prefix(stream_id.as_slice()) {
+            Some(commit) => commit,
+            None => continue,
+        };
+        let commits = if commit == [0] {
+            Some(1)
+        } else {
+            let cid = cid_string(commit);
+            stream.log.iter().position(|c| c.cid == cid).map(|i| i + 1)
+        };
+        if let Some(commits) = commits {
+            return Ok((
+                StatusCode::OK,
+                json!({"streamId": stream.id, "state": stream.state_at(commits)}),
+            ));
+        }
+    }
+    Err(not_found())
+}
+
+/// Load the streams of a multiquery, leaving out the ones that cannot be loaded. Paths are
+/// ignored.
+fn multi_query(state: &State, req: MultiQueryRequest) -> (StatusCode, Value) {
+    let states: serde_json::Map<_, _> = req
+        .queries
+        .into_iter()
+        .filter_map(|q| {
+            let (_, resp) = load(state, &q.stream_id).ok()?;
+            Some((q.stream_id, resp["state"].clone()))
+        })
+        .collect();
+    (StatusCode::OK, Value::Object(states))
+}
+
+/// Complete a pending anchor, so the next load reports the stream as anchored
+fn complete_anchor(state: &mut State, id: &str) {
+    if let Some(stream) = state.stream_mut(id) {
+        if stream.anchor_status == "PENDING" {
+            stream.anchor_status = "ANCHORED";
+        }
+    }
+}
+
+fn commits(state: &State, id: &str) -> HandlerResult {
+    let stream = state
+        .stream(id)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Stream {} not found", id)))?;
+    Ok((
+        StatusCode::OK,
+        json!({"streamId": stream.id, "commits": stream.commits()}),
+    ))
+}
+
+fn update(state: &mut State, req: UpdateRequest) -> HandlerResult {
+    let stream = state.stream_mut(&req.stream_id).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Stream {} not found", req.stream_id),
+        )
+    })?;
+    let linked_block = req
+        .commit
+        .linked_block
+        .as_ref()
+        .ok_or_else(|| bad_request("Commit has no linked block"))?;
+    let payload = decode_block(linked_block)?;
+    let tip = &stream.log.last().expect("streams have a genesis").cid;
+    if payload.get("prev").and_then(Value::as_str) != Some(tip.as_str()) {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("Commit is not based on the tip {} of the stream", tip),
+        ));
+    }
+    let patch: json_patch::Patch =
+        serde_json::from_value(payload.get("data").cloned().unwrap_or(json!([])))
+            .map_err(bad_request)?;
+    let mut content = stream.content.clone();
+    if content.is_null() {
+        content = json!({});
+    }
+    json_patch::patch(&mut content, &patch).map_err(bad_request)?;
+    let jws = req
+        .commit
+        .jws
+        .ok_or_else(|| bad_request("Commit has no jws"))?;
+    let commit_cid = jws_cid(&jws)?;
+    stream.content = content.clone();
+    stream.anchor_status = req.opts.anchor_status();
+    stream.log.push(MockCommit {
+        cid: cid_string(&commit_cid),
+        value: signed_value(&jws, linked_block, req.commit.cacao_block.as_deref()),
+        content,
+    });
+    Ok((StatusCode::OK, stream.response()))
+}
+
+/// Value of a signed commit, with the cacao block when it was signed by a did session
+fn signed_value(jws: &Value, linked_block: &str, cacao_block: Option<&str>) -> Value {
+    let mut value = json!({"jws": jws, "linkedBlock": linked_block});
+    if let Some(cacao_block) = cacao_block {
+        value["cacaoBlock"] = json!(cacao_block);
+    }
+    value
+}
+
+fn query(state: &State, req: QueryRequest) -> HandlerResult {
+    let matching: Vec<(usize, &MockStream)> = state
+        .streams
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.r#type == MODEL_INSTANCE_DOCUMENT && s.model == req.model)
+        .filter(|(_, s)| {
+            req.query_filters
+                .as_ref()
+                .map(|f| f.matches(&s.content))
+                .unwrap_or(true)
+        })
+        .collect();
+    let (page, has_next_page, has_previous_page) = if let Some(last) = req.last {
+        let before = req.before.as_deref().map(decode_cursor).transpose()?;
+        let candidates: Vec<_> = matching
+            .into_iter()
+            .filter(|(i, _)| before.map(|b| *i < b).unwrap_or(true))
+            .collect();
+        let start = candidates.len().saturating_sub(last);
+        (candidates[start..].to_vec(), false, start > 0)
+    } else {
+        let first = req.first.unwrap_or(100);
+        let after = req.after.as_deref().map(decode_cursor).transpose()?;
+        let candidates: Vec<_> = matching
+            .into_iter()
+            .filter(|(i, _)| after.map(|a| *i > a).unwrap_or(true))
+            .collect();
+        let has_next = candidates.len() > first;
+        (
+            candidates.into_iter().take(first).collect(),
+            has_next,
+            false,
+        )
+    };
+    let edges: Vec<Value> = page
+        .iter()
+        .map(|(i, s)| {
+            json!({
+                "cursor": encode_cursor(*i),
+                "node": {"content": s.content, "log": s.commits()},
+            })
+        })
+        .collect();
+    Ok((
+        StatusCode::OK,
+        json!({
+            "edges": edges,
+            "pageInfo": {
+                "hasNextPage": has_next_page,
+                "hasPreviousPage": has_previous_page,
+                "startCursor": page.first().map(|(i, _)| encode_cursor(*i)),
+                "endCursor": page.last().map(|(i, _)| encode_cursor(*i)),
+            },
+        }),
+    ))
+}
+
+fn admin_code(state: &mut State) -> (StatusCode, Value) {
+    let code = format!("{:032x}", rand::random::<u128>());
+    if std::mem::take(&mut state.expire_next_code) {
+        state.expired_codes.push(code.clone());
+    } else {
+        state.codes.push(code.clone());
+    }
+    state.code_requests += 1;
+    (StatusCode::OK, json!({ "code": code }))
+}
+
+/// Body of an admin request, discarding its admin code as the node does once a code is used
+fn admin_payload(state: &mut State, jws: &str) -> Result<Value, (StatusCode, String)> {
+    let payload = jws
+        .split('.')
+        .nth(1)
+        .ok_or_else(|| bad_request("Invalid admin jws"))?;
+    let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload)
+        .map_err(bad_request)?;
+    let payload: AdminPayload = serde_json::from_slice(&payload).map_err(bad_request)?;
+    // Messages of the js-ceramic daemon
+    if let Some(i) = state.codes.iter().position(|code| *code == payload.code) {
+        state.codes.remove(i);
+        Ok(payload.request_body)
+    } else if state.expired_codes.contains(&payload.code) {
+        Err((
+            StatusCode::UNAUTHORIZED,
+            "Unauthorized access: expired admin code - admin codes are only valid for 60 seconds"
+                .to_string(),
+        ))
+    } else {
+        Err((
+            StatusCode::UNAUTHORIZED,
+            "Unauthorized access: invalid/already used admin code".to_string(),
+        ))
+    }
+}
+
+/// Model ids of an admin model data request
+fn model_data(state: &mut State, jws: &str) -> Result<Vec<String>, (StatusCode, String)> {
+    let body = admin_payload(state, jws)?;
+    body.get("modelData")
+        .and_then(Value::as_array)
+        .ok_or_else(|| bad_request("Missing modelData"))?
+        .iter()
+        .map(|model| {
+            model
+                .get("streamID")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| bad_request("Missing streamID"))
+        })
+        .collect()
+}
+
+/// Index a batch of models, rejecting the whole batch if any of them is not a known model
+fn index(state: &mut State, jws: &str) -> HandlerResult {
+    let models = model_data(state, jws)?;
+    if let Some(id) = models
+        .iter()
+        .find(|id| state.stream(id).map(|s| s.r#type) != Some(MODEL))
+    {
+        return Err(bad_request(format!("Model {} not found", id)));
+    }
+    for id in models {
+        if !state.indexed.contains(&id) {
+            state.indexed.push(id);
+        }
+    }
+    Ok((StatusCode::OK, json!({ "result": "success" })))
+}
+
+fn unindex(state: &mut State, jws: &str) -> HandlerResult {
+    let models = model_data(state, jws)?;
+    state.indexed.retain(|id| !models.contains(id));
+    Ok((StatusCode::OK, json!({ "result": "success" })))
+}
+
+fn models(state: &mut State, auth: Option<String>) -> HandlerResult {
+    admin_payload(state, &auth.unwrap_or_default())?;
+    Ok((StatusCode::OK, json!({ "models": state.indexed })))
+}
+
+fn status(state: &mut State, auth: Option<String>) -> HandlerResult {
+    admin_payload(state, &auth.unwrap_or_default())?;
+    Ok((
+        StatusCode::OK,
+        json!({
+            "runId": "mock",
+            "uptimeMs": 0,
+            "network": "inmemory",
+            "anchor": {
+                "anchorServiceUrl": "",
+                "ethereumRpcEndpoint": null,
+                "chainId": "inmemory:12345",
+            },
+            "ipfs": {"peerId": "mock", "addresses": []},
+            "composeDb": {"indexedModels": state.indexed, "syncs": null},
+        }),
+    ))
+}
+
+fn pins(state: &State, id: Option<&str>) -> (StatusCode, Value) {
+    let pinned: Vec<_> = state
+        .pinned
+        .iter()
+        .filter(|pinned| id.is_none() || id == Some(pinned.as_str()))
+        .collect();
+    (StatusCode::OK, json!({ "pinnedStreamIds": pinned }))
+}
+
+fn pin(state: &mut State, id: &str) -> HandlerResult {
+    if state.stream(id).is_none() {
+        return Err((StatusCode::NOT_FOUND, format!("Stream {} not found", id)));
+    }
+    if !state.pinned.iter().any(|p| p == id) {
+        state.pinned.push(id.to_string());
+    }
+    Ok((StatusCode::OK, json!({ "streamId": id, "isPinned": true })))
+}
+
+fn unpin(state: &mut State, id: &str) -> (StatusCode, Value) {
+    state.pinned.retain(|p| p != id);
+    (StatusCode::OK, json!({ "streamId": id, "isPinned": false }))
+}
+
+fn encode_cursor(pos: usize) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(pos.to_string())
+}
+
+fn decode_cursor(cursor: &str) -> Result<usize, (StatusCode, String)> {
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(cursor)
+        .map_err(bad_request)?;
+    String::from_utf8_lossy(&bytes).parse().map_err(bad_request)
+}
+
+fn decode_base64(s: &str) -> Result<Vec<u8>, (StatusCode, String)> {
+    let s = s.trim_end_matches('=');
+    let engine = if s.contains(['-', '_']) {
+        base64::engine::general_purpose::URL_SAFE_NO_PAD
+    } else {
+        base64::engine::general_purpose::STANDARD_NO_PAD
+    };
+    engine.decode(s).map_err(bad_request)
+}
+
+/// Decode a base64 encoded dag-cbor block to json, rendering links as cid strings
+fn decode_block(block: &str) -> Result<Value, (StatusCode, String)> {
+    decode_cbor(&decode_base64(block)?)
+}
+
+fn decode_cbor(bytes: &[u8]) -> Result<Value, (StatusCode, String)> {
+    let value: ciborium::value::Value = ciborium::de::from_reader(bytes).map_err(bad_request)?;
+    Ok(cbor_to_json(value))
+}
+
+fn cbor_to_json(value: ciborium::value::Value) -> Value {
+    use ciborium::value::Value as Cbor;
+    match value {
+        Cbor::Null => Value::Null,
+        Cbor::Bool(b) => Value::Bool(b),
+        Cbor::Integer(i) => {
+            let i: i128 = i.into();
+            i64::try_from(i)
+                .map(Value::from)
+                .unwrap_or_else(|_| Value::from(i as f64))
+        }
+        Cbor::Float(f) => Value::from(f),
+        Cbor::Text(s) => Value::String(s),
+        Cbor::Bytes(b) => Value::String(base64::engine::general_purpose::STANDARD.encode(b)),
+        Cbor::Array(values) => Value::Array(values.into_iter().map(cbor_to_json).collect()),
+        Cbor::Map(entries) => Value::Object(
+            entries
+                .into_iter()
+                .filter_map(|(k, v)| match k {
+                    Cbor::Text(k) => Some((k, cbor_to_json(v))),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        // dag-cbor links are tag 42 over the cid bytes prefixed with the identity multibase
+        Cbor::Tag(42, link) => match *link {
+            Cbor::Bytes(b) if b.first() == Some(&0) => Value::String(cid_string(&b[1..])),
+            other => cbor_to_json(other),
+        },
+        Cbor::Tag(_, value) => cbor_to_json(*value),
+        _ => Value::Null,
+    }
+}
+
+fn cid(codec: &[u8], bytes: &[u8]) -> Vec<u8> {
+    let mut cid = vec![0x01];
+    cid.extend_from_slice(codec);
+    cid.extend_from_slice(&[0x12, 0x20]);
+    cid.extend_from_slice(&Sha256::digest(bytes));
+    cid
+}
+
+/// Cid of a jws, hashing its dag-jose block: the payload and signatures as cbor bytes
+fn jws_cid(jws: &Value) -> Result<Vec<u8>, (StatusCode, String)> {
+    use ciborium::value::Value as Cbor;
+    let bytes = |value: Option<&Value>| match value.and_then(Value::as_str) {
+        Some(value) => Ok(Cbor::Bytes(decode_base64(value)?)),
+        None => Err(bad_request("Invalid jws")),
+    };
+    let mut signatures = Vec::new();
+    for sig in jws
+        .get("signatures")
+        .and_then(Value::as_array)
+        .ok_or_else(|| bad_request("Invalid jws"))?
+    {
+        signatures.push(Cbor::Map(vec![
+            (Cbor::Text("protected".into()), bytes(sig.get("protected"))?),
+            (Cbor::Text("signature".into()), bytes(sig.get("signature"))?),
+        ]));
+    }
+    let block = Cbor::Map(vec![
+        (Cbor::Text("payload".into()), bytes(jws.get("payload"))?),
+        (Cbor::Text("signatures".into()), Cbor::Array(signatures)),
+    ]);
+    let mut encoded = Vec::new();
+    ciborium::ser::into_writer(&block, &mut encoded).map_err(bad_request)?;
+    Ok(cid(&DAG_JOSE, &encoded))
+}
+
+fn cid_string(cid: &[u8]) -> String {
+    multibase::encode(multibase::Base::Base32Lower, cid)
+}
+
+fn stream_id(r#type: u64, cid: &[u8]) -> String {
+    let mut bytes = STREAM_ID_CODEC.to_vec();
+    let mut t = r#type;
+    loop {
+        let byte = (t & 0x7f) as u8;
+        t >>= 7;
+        if t == 0 {
+            bytes.push(byte);
+            break;
+        }
+        bytes.push(byte | 0x80);
+    }
+    bytes.extend_from_slice(cid);
+    base36(&bytes)
+}
+
+fn stream_id_from_base64(model: &str) -> Result<String, (StatusCode, String)> {
+    Ok(base36(&decode_base64(model)?))
+}
+
+fn base36(bytes: &[u8]) -> String {
+    multibase::encode(multibase::Base::Base36Lower, bytes)
+}
+
+fn decode_base36(s: &str) -> Option<Vec<u8>> {
+    match multibase::decode(s) {
+        Ok((multibase::Base::Base36Lower, bytes)) => Some(bytes),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::api::Pagination;
+    use crate::remote::CeramicRemoteHttpClient;
+    use crate::session::tests::signer;
+    use crate::{GetRootSchema, ModelAccountRelation, ModelDefinition};
+    use ceramic_event::{JwkSigner, StreamId};
+    use schemars::JsonSchema;
+    use serde::Serialize;
+
+    #[derive(Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+    #[schemars(rename_all = "camelCase", deny_unknown_fields)]
+    pub(crate) struct Ball {
+        pub(crate) radius: i32,
+        pub(crate) red: i32,
+    }
+
+    impl GetRootSchema for Ball {}
+
+    pub(crate) async fn client(mock: &MockCeramic) -> CeramicRemoteHttpClient<JwkSigner> {
+        CeramicRemoteHttpClient::new(signer().await, mock.url())
+    }
+
+    /// Mock node with a client and a "TestBall" model created with `relation`
+    pub(crate) async fn setup(
+        relation: ModelAccountRelation,
+    ) -> (MockCeramic, CeramicRemoteHttpClient<JwkSigner>, StreamId) {
+        let mock = MockCeramic::start().await.unwrap();
+        let ceramic = client(&mock).await;
+        let model = ModelDefinition::new::<Ball>("TestBall", relation).unwrap();
+        let model = ceramic.create_model(&model).await.unwrap();
+        (mock, ceramic, model)
+    }
+
+    #[test]
+    fn should_encode_multibase() {
+        assert_eq!(base36(b"yes mani !"), "k2lcpzo5yikidynfl");
+        assert_eq!(cid_string(b"yes mani !"), "bpfsxgidnmfxgsibb");
+    }
+
+    #[tokio::test]
+    async fn should_create_update_and_query() {
+        let (mock, ceramic, model) = setup(ModelAccountRelation::List).await;
+        assert_eq!(ceramic.healthcheck().await.unwrap(), "Alive!");
+        ceramic.index_model(&model).await.unwrap();
+        let indexed = ceramic.list_indexed_models().await.unwrap();
+        assert_eq!(indexed.models, vec![model.clone()]);
+
+        let stream_id = ceramic
+            .create_list_instance(&model, &Ball { radius: 1, red: 2 })
+            .await
+            .unwrap();
+        ceramic
+            .create_list_instance(&model, &Ball { radius: 2, red: 3 })
+            .await
+            .unwrap();
+        let patch = json_patch::Patch(vec![json_patch::PatchOperation::Replace(
+            json_patch::ReplaceOperation {
+                path: "/red".to_string(),
+                value: json!(5),
+            },
+        )]);
+        ceramic.update(&model, &stream_id, patch).await.unwrap();
+        let ball: Ball = ceramic.get_as(&stream_id).await.unwrap();
+        assert_eq!(ball, Ball { radius: 1, red: 5 });
+        let commits = ceramic.commits(&stream_id).await.unwrap();
+        assert_eq!(commits.commits.len(), 2);
+        let genesis = ceramic
+            .get_at_commit(&crate::CommitId::genesis(stream_id.clone()))
+            .await
+            .unwrap();
+        assert_eq!(
+            genesis.state.unwrap().content,
+            json!({"radius": 1, "red": 2})
+        );
+
+        let filter = FilterQuery::Where(
+            [(
+                "red".to_string(),
+                crate::OperationFilter::GreaterThan(4.into()),
+            )]
+            .into_iter()
+            .collect(),
+        );
+        let res = ceramic
+            .query(&model, Some(filter), Pagination::default())
+            .await
+            .unwrap();
+        assert_eq!(res.edges.len(), 1);
+        let all: Vec<crate::api::TypedQueryDocument<Ball>> = ceramic
+            .query_all_as(
+                &model,
+                None,
+                Pagination::First {
+                    first: 1,
+                    after: None,
+                },
+                10,
+            )
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(mock.stream_ids().len(), 3);
+    }
+}
@@ -1,21 +1,36 @@
+use crate::{Error, Result};
 use ceramic_event::StreamId;
 use schemars::schema::RootSchema;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
 
-/// Type of account relation, whether single instance per account or multiple (list)
-#[derive(Debug, Deserialize, Serialize)]
+/// Version of model definitions without interfaces
+pub const MODEL_VERSION_1: &str = "1.0";
+/// Version of model definitions supporting interfaces and immutable fields
+pub const MODEL_VERSION_2: &str = "2.0";
+
+/// Type of account relation, whether single instance per account, multiple (list), or one per
+/// set of unique field values
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase", tag = "type")]
 pub enum ModelAccountRelation {
     /// Multiple instances of model for account
     List,
     /// Single instead of model for account
     Single,
+    /// Single instance of model for account per combination of values of `fields`
+    Set {
+        /// Fields whose values identify an instance
+        fields: Vec<String>,
+    },
+    /// No instances of model, for interfaces that only describe other models
+    None,
 }
 
 /// How a model is related, whether by account or document
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase", tag = "type")]
 pub enum ModelRelationDefinition {
     /// Related to the account
@@ -28,7 +43,7 @@ pub enum ModelRelationDefinition {
 }
 
 /// Describe how model views are created
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase", tag = "type")]
 pub enum ModelViewDefinition {
     /// View at account level
@@ -59,15 +74,15 @@ pub enum ModelViewDefinition {
 }
 
 /// Schema encoded as Cbor
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[repr(transparent)]
 pub struct CborSchema(serde_json::Value);
 
 /// Definition of a model for use when creating instances
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ModelDefinition {
-    version: &'static str,
+    version: String,
     name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
@@ -77,6 +92,12 @@ pub struct ModelDefinition {
     relations: HashMap<String, ModelRelationDefinition>,
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     views: HashMap<String, ModelViewDefinition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    interface: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    implements: Option<Vec<StreamId>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    immutable_fields: Option<Vec<String>>,
 }
 
 impl ModelDefinition {
@@ -84,22 +105,78 @@ impl ModelDefinition {
     pub fn new<T: GetRootSchema>(
         name: &str,
         account_relation: ModelAccountRelation,
-    ) -> anyhow::Result<Self> {
-        let schema = T::root_schema();
-        let schema = serde_json::to_value(&schema)?;
+    ) -> Result<Self> {
+        Self::from_schema(name, account_relation, &T::root_schema())
+    }
+
+    /// Create a new definition from a json schema
+    pub fn from_schema(
+        name: &str,
+        account_relation: ModelAccountRelation,
+        schema: &RootSchema,
+    ) -> Result<Self> {
+        let schema = serde_json::to_value(schema)?;
         Ok(Self {
-            version: "1.0",
+            version: MODEL_VERSION_1.to_string(),
             name: name.to_string(),
             description: None,
             schema: CborSchema(schema),
             account_relation,
             relations: HashMap::default(),
             views: HashMap::default(),
+            interface: None,
+            implements: None,
+            immutable_fields: None,
         })
     }
 
+    /// Version of this definition
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Name of the model
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Description of the model
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Relations of the model, by property
+    pub fn relations(&self) -> &HashMap<String, ModelRelationDefinition> {
+        &self.relations
+    }
+
+    /// Views of the model, by property
+    pub fn views(&self) -> &HashMap<String, ModelViewDefinition> {
+        &self.views
+    }
+
+    /// Account relation of the model
+    pub fn account_relation(&self) -> &ModelAccountRelation {
+        &self.account_relation
+    }
+
+    /// Whether this definition is an interface, which can be implemented but not instantiated
+    pub fn is_interface(&self) -> bool {
+        self.interface.unwrap_or_default()
+    }
+
+    /// Interfaces implemented by this definition
+    pub fn implements(&self) -> &[StreamId] {
+        self.implements.as_deref().unwrap_or_default()
+    }
+
+    /// Fields that cannot be changed once an instance is created
+    pub fn immutable_fields(&self) -> &[String] {
+        self.immutable_fields.as_deref().unwrap_or_default()
+    }
+
     /// Schema of this definition
-    pub fn schema(&self) -> anyhow::Result<RootSchema> {
+    pub fn schema(&self) -> Result<RootSchema> {
         let s = serde_json::from_value(self.schema.0.clone())?;
         Ok(s)
     }
@@ -121,6 +198,116 @@ impl ModelDefinition {
         self.views.insert(key, view);
         self
     }
+
+    /// Mark this definition as an interface, upgrading it to a v2 definition
+    pub fn as_interface(&mut self) -> &mut Self {
+        self.upgrade();
+        self.interface = Some(true);
+        self
+    }
+
+    /// Declare an interface implemented by this definition, upgrading it to a v2 definition
+    pub fn with_implements(&mut self, interface: StreamId) -> &mut Self {
+        self.upgrade();
+        self.implements.get_or_insert_with(Vec::new).push(interface);
+        self
+    }
+
+    /// Mark a field as immutable, upgrading this definition to a v2 definition
+    pub fn with_immutable_field(&mut self, field: String) -> &mut Self {
+        self.upgrade();
+        self.immutable_fields
+            .get_or_insert_with(Vec::new)
+            .push(field);
+        self
+    }
+
+    /// Check that immutable and set relation fields are properties of the schema, and that it is
+    /// compatible with each implemented interface. An implementing schema must declare every
+    /// property of an interface with the same schema, and require every property it requires.
+    pub fn validate(&self, interfaces: &[ModelDefinition]) -> Result<()> {
+        let properties = self.schema_properties();
+        for field in self.immutable_fields() {
+            if !properties.contains_key(field) {
+                return Err(self.invalid(format!("immutable field {} is not in the schema", field)));
+            }
+        }
+        if let ModelAccountRelation::Set { fields } = &self.account_relation {
+            if fields.is_empty() {
+                return Err(self.invalid("set relation has no fields".to_string()));
+            }
+            if let Some(field) = fields.iter().find(|f| !properties.contains_key(*f)) {
+                return Err(self.invalid(format!("set field {} is not in the schema", field)));
+            }
+        }
+        let required = self.schema_required();
+        for interface in interfaces {
+            if !interface.is_interface() {
+                return Err(self.invalid(format!("{} is not an interface", interface.name)));
+            }
+            for (key, schema) in &interface.schema_properties() {
+                if properties.get(key) != Some(schema) {
+                    return Err(self.invalid(format!(
+                        "property {} does not match interface {}",
+                        key, interface.name
+                    )));
+                }
+            }
+            for key in interface.schema_required() {
+                if !required.contains(&key) {
+                    return Err(self.invalid(format!(
+                        "property {} is required by interface {}",
+                        key, interface.name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that `unique` has a value for each field of the set account relation of the model
+    pub fn validate_unique(&self, unique: &[String]) -> Result<()> {
+        match &self.account_relation {
+            ModelAccountRelation::Set { fields } if fields.len() == unique.len() => Ok(()),
+            ModelAccountRelation::Set { fields } => Err(self.invalid(format!(
+                "expected {} unique values but got {}",
+                fields.len(),
+                unique.len()
+            ))),
+            _ => Err(self.invalid("model does not have a set account relation".to_string())),
+        }
+    }
+
+    fn upgrade(&mut self) {
+        self.version = MODEL_VERSION_2.to_string();
+        self.interface.get_or_insert(false);
+        self.implements.get_or_insert_with(Vec::new);
+    }
+
+    fn schema_properties(&self) -> serde_json::Map<String, Value> {
+        self.schema
+            .0
+            .get("properties")
+            .and_then(Value::as_object)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn schema_required(&self) -> Vec<&str> {
+        self.schema
+            .0
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|r| r.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    fn invalid(&self, message: String) -> Error {
+        Error::InvalidModel {
+            model: self.name.clone(),
+            message,
+        }
+    }
 }
 
 /// A trait which helps convert a type that implements `JsonSchema` into a `RootSchema` with
@@ -137,3 +324,107 @@ pub trait GetRootSchema: JsonSchema {
         gen.into_root_schema_for::<Self>()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(JsonSchema)]
+    #[allow(dead_code)]
+    struct Shape {
+        sides: u32,
+    }
+
+    impl GetRootSchema for Shape {}
+
+    #[derive(JsonSchema)]
+    #[allow(dead_code)]
+    struct Square {
+        sides: u32,
+        length: f64,
+    }
+
+    impl GetRootSchema for Square {}
+
+    #[derive(JsonSchema)]
+    #[allow(dead_code)]
+    struct Circle {
+        radius: f64,
+    }
+
+    impl GetRootSchema for Circle {}
+
+    #[test]
+    fn should_validate_implemented_interfaces() {
+        let mut shape = ModelDefinition::new::<Shape>("Shape", ModelAccountRelation::List).unwrap();
+        shape.as_interface();
+        assert_eq!(shape.version(), MODEL_VERSION_2);
+        assert!(shape.is_interface());
+
+        let mut square =
+            ModelDefinition::new::<Square>("Square", ModelAccountRelation::List).unwrap();
+        assert_eq!(square.version(), MODEL_VERSION_1);
+        square.with_immutable_field("sides".to_string());
+        assert_eq!(square.version(), MODEL_VERSION_2);
+        assert!(!square.is_interface());
+        square.validate(std::slice::from_ref(&shape)).unwrap();
+
+        let circle = ModelDefinition::new::<Circle>("Circle", ModelAccountRelation::List).unwrap();
+        assert!(matches!(
+            circle.validate(std::slice::from_ref(&shape)),
+            Err(Error::InvalidModel { .. })
+        ));
+        assert!(square.validate(std::slice::from_ref(&circle)).is_err());
+
+        square.with_immutable_field("color".to_string());
+        assert!(square.validate(&[]).is_err());
+    }
+
+    #[test]
+    fn should_validate_set_fields() {
+        let relation = ModelAccountRelation::Set {
+            fields: vec!["sides".to_string()],
+        };
+        let shape = ModelDefinition::new::<Shape>("Shape", relation).unwrap();
+        shape.validate(&[]).unwrap();
+        shape.validate_unique(&["4".to_string()]).unwrap();
+        assert!(shape.validate_unique(&[]).is_err());
+        assert!(shape
+            .validate_unique(&["4".to_string(), "red".to_string()])
+            .is_err());
+        assert_eq!(
+            serde_json::to_value(shape.account_relation()).unwrap(),
+            serde_json::json!({"type": "set", "fields": ["sides"]})
+        );
+        let relation = ModelAccountRelation::Set {
+            fields: vec!["radius".to_string()],
+        };
+        let shape = ModelDefinition::new::<Shape>("Shape", relation).unwrap();
+        assert!(shape.validate(&[]).is_err());
+    }
+
+    #[test]
+    fn should_deserialize_definition() {
+        let model = "kjzl6hvfrbw6c8apa5yce6ah3fsz9sgrh6upniy0tz8z76gdm169ds3tf8c051t";
+        let json = serde_json::json!({
+            "version": "2.0",
+            "name": "Named",
+            "schema": {"type": "object", "properties": {"name": {"type": "string"}}},
+            "accountRelation": {"type": "none"},
+            "interface": true,
+            "implements": [model],
+            "relations": {"owner": {"type": "document", "model": model}},
+            "views": {"author": {"type": "documentAccount"}},
+        });
+        let definition: ModelDefinition = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(definition.version(), MODEL_VERSION_2);
+        assert!(definition.is_interface());
+        assert!(matches!(
+            definition.account_relation(),
+            ModelAccountRelation::None
+        ));
+        assert_eq!(definition.implements().len(), 1);
+        assert!(definition.relations().contains_key("owner"));
+        assert_eq!(serde_json::to_value(&definition).unwrap(), json);
+    }
+}
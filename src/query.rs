@@ -1,4 +1,5 @@
-use serde::Serialize;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
 
 /// Valid values for operation Filter
@@ -15,6 +16,39 @@ pub enum NumberFilter {
     F64(f64),
 }
 
+impl NumberFilter {
+    fn as_f64(&self) -> f64 {
+        match self {
+            Self::I64(v) => *v as f64,
+            Self::I32(v) => *v as f64,
+            Self::F32(v) => *v as f64,
+            Self::F64(v) => *v,
+        }
+    }
+
+    fn compare(&self, value: Option<&Value>) -> Option<std::cmp::Ordering> {
+        value
+            .and_then(Value::as_f64)
+            .and_then(|v| v.partial_cmp(&self.as_f64()))
+    }
+}
+
+impl<'de> Deserialize<'de> for NumberFilter {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let number = serde_json::Number::deserialize(deserializer)?;
+        if let Some(v) = number.as_i64() {
+            Ok(Self::I64(v))
+        } else if let Some(v) = number.as_f64() {
+            Ok(Self::F64(v))
+        } else {
+            Err(serde::de::Error::custom(format!(
+                "number out of range: {}",
+                number
+            )))
+        }
+    }
+}
+
 impl From<i64> for NumberFilter {
     fn from(value: i64) -> Self {
         Self::I64(value)
@@ -40,7 +74,7 @@ impl From<f32> for NumberFilter {
 }
 
 /// Valid values for operation Filter
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum ValueFilter {
     /// String value
@@ -49,6 +83,15 @@ pub enum ValueFilter {
     Number(NumberFilter),
 }
 
+impl ValueFilter {
+    fn matches(&self, value: &Value) -> bool {
+        match self {
+            Self::String(s) => value.as_str() == Some(s.as_str()),
+            Self::Number(n) => value.as_f64() == Some(n.as_f64()),
+        }
+    }
+}
+
 impl From<&str> for ValueFilter {
     fn from(value: &str) -> Self {
         Self::from(value.to_string())
@@ -86,7 +129,7 @@ impl From<f32> for ValueFilter {
 }
 
 /// Valid values for operation Filter
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum EqualValueFilter {
     /// Boolean value
@@ -95,6 +138,15 @@ pub enum EqualValueFilter {
     Value(ValueFilter),
 }
 
+impl EqualValueFilter {
+    fn matches(&self, value: &Value) -> bool {
+        match self {
+            Self::Boolean(b) => value.as_bool() == Some(*b),
+            Self::Value(v) => v.matches(value),
+        }
+    }
+}
+
 impl From<bool> for EqualValueFilter {
     fn from(value: bool) -> Self {
         Self::Boolean(value)
@@ -138,7 +190,7 @@ impl From<f32> for EqualValueFilter {
 }
 
 /// Operation Filter
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum OperationFilter {
     /// Filter by null or not null
@@ -161,8 +213,56 @@ pub enum OperationFilter {
     GreaterThanOrEqualTo(NumberFilter),
 }
 
+impl OperationFilter {
+    /// Whether a field value matches this operation, where `None` is a missing or null field.
+    /// As in the node's sql queries, comparisons against a missing value never match.
+    pub fn matches(&self, value: Option<&Value>) -> bool {
+        self.evaluate(value) == Some(true)
+    }
+
+    /// Sql truth value of this operation on a field value, `None` being unknown as for any
+    /// comparison against a missing value
+    fn evaluate(&self, value: Option<&Value>) -> Option<bool> {
+        use std::cmp::Ordering;
+        let value = value.filter(|v| !v.is_null());
+        if let Self::IsNull(is_null) = self {
+            return Some(value.is_none() == *is_null);
+        }
+        let value = value?;
+        let compare = |n: &NumberFilter| n.compare(Some(value));
+        Some(match self {
+            Self::IsNull(_) => unreachable!("handled above"),
+            Self::EqualTo(filter) => filter.matches(value),
+            Self::NotEqualTo(filter) => !filter.matches(value),
+            Self::In(filters) => filters.iter().any(|f| f.matches(value)),
+            Self::NotIn(filters) => !filters.iter().any(|f| f.matches(value)),
+            Self::LessThan(n) => compare(n) == Some(Ordering::Less),
+            Self::LessThanOrEqualTo(n) => {
+                matches!(compare(n), Some(Ordering::Less | Ordering::Equal))
+            }
+            Self::GreaterThan(n) => compare(n) == Some(Ordering::Greater),
+            Self::GreaterThanOrEqualTo(n) => {
+                matches!(compare(n), Some(Ordering::Greater | Ordering::Equal))
+            }
+        })
+    }
+}
+
+/// Sql conjunction of truth values, false if any is false and otherwise unknown if any is
+fn all_of(values: impl Iterator<Item = Option<bool>>) -> Option<bool> {
+    let mut result = Some(true);
+    for value in values {
+        match value {
+            Some(false) => return Some(false),
+            None => result = None,
+            Some(true) => {}
+        }
+    }
+    result
+}
+
 /// Combination query
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CombinationQuery(Vec<FilterQuery>);
 
 impl CombinationQuery {
@@ -189,7 +289,7 @@ macro_rules! or {
 }
 
 /// Filter Query
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum FilterQuery {
     /// Filter by where
     #[serde(rename = "where")]
@@ -205,6 +305,30 @@ pub enum FilterQuery {
     Not(Box<FilterQuery>),
 }
 
+impl FilterQuery {
+    /// Whether the content of a document matches this filter. Filters are evaluated with sql's
+    /// three valued logic, so negating a comparison against a missing field does not match
+    /// either.
+    pub fn matches(&self, document: &Value) -> bool {
+        self.evaluate(document) == Some(true)
+    }
+
+    fn evaluate(&self, document: &Value) -> Option<bool> {
+        match self {
+            Self::Where(fields) => all_of(
+                fields
+                    .iter()
+                    .map(|(field, op)| op.evaluate(document.get(field))),
+            ),
+            Self::And(filters) => all_of(filters.0.iter().map(|f| f.evaluate(document))),
+            Self::Or(filters) => {
+                all_of(filters.0.iter().map(|f| f.evaluate(document).map(|v| !v))).map(|v| !v)
+            }
+            Self::Not(filter) => filter.evaluate(document).map(|v| !v),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -304,4 +428,94 @@ mod tests {
             r#"{"and":[{"or":[{"not":{"where":{"id":{"isNull":false}}}},{"where":{"id":{"isNull":false}}}]},{"or":[{"not":{"where":{"id2":{"notEqualTo":2}}}},{"where":{"id2":{"notEqualTo":2}}}]},{"or":[{"not":{"where":{"id3":{"notIn":[3.0,4.0]}}}},{"where":{"id3":{"notIn":[3.0,4.0]}}}]}]}"#
         );
     }
+
+    #[test]
+    fn should_match_where() {
+        let doc = serde_json::json!({"id": "1", "count": 3, "active": true, "missing": null});
+        let mut where_filter = HashMap::new();
+        where_filter.insert("id".to_string(), OperationFilter::EqualTo("1".into()));
+        where_filter.insert("count".to_string(), OperationFilter::EqualTo(3.into()));
+        where_filter.insert("active".to_string(), OperationFilter::EqualTo(true.into()));
+        where_filter.insert("missing".to_string(), OperationFilter::IsNull(true));
+        assert!(FilterQuery::Where(where_filter).matches(&doc));
+
+        let mut where_filter = HashMap::new();
+        where_filter.insert("id".to_string(), OperationFilter::NotEqualTo("1".into()));
+        assert!(!FilterQuery::Where(where_filter).matches(&doc));
+    }
+
+    #[test]
+    fn should_match_comparisons() {
+        let doc = serde_json::json!({"count": 3, "ratio": 0.5});
+        let matches = |field: &str, op: OperationFilter| {
+            let mut where_filter = HashMap::new();
+            where_filter.insert(field.to_string(), op);
+            FilterQuery::Where(where_filter).matches(&doc)
+        };
+        assert!(matches("count", OperationFilter::LessThan(4.into())));
+        assert!(!matches("count", OperationFilter::LessThan(3.into())));
+        assert!(matches(
+            "count",
+            OperationFilter::LessThanOrEqualTo(3i64.into())
+        ));
+        assert!(matches(
+            "ratio",
+            OperationFilter::GreaterThan(0.25f32.into())
+        ));
+        assert!(!matches(
+            "ratio",
+            OperationFilter::GreaterThanOrEqualTo(1.0.into())
+        ));
+        assert!(matches(
+            "count",
+            OperationFilter::In(vec![1.into(), 3.into()])
+        ));
+        assert!(matches("count", OperationFilter::NotIn(vec!["3".into()])));
+        assert!(!matches("absent", OperationFilter::NotIn(vec![1.into()])));
+        assert!(!matches("absent", OperationFilter::NotEqualTo(1.into())));
+        assert!(!matches("absent", OperationFilter::GreaterThan(1.into())));
+    }
+
+    #[test]
+    fn should_match_combinations() {
+        let doc = serde_json::json!({"id": 1, "id2": 2});
+        let mut where_filter1 = HashMap::new();
+        where_filter1.insert("id".to_string(), OperationFilter::EqualTo(1.into()));
+        let mut where_filter2 = HashMap::new();
+        where_filter2.insert("id2".to_string(), OperationFilter::EqualTo(3.into()));
+        let filter1 = FilterQuery::Where(where_filter1);
+        let filter2 = FilterQuery::Where(where_filter2);
+        assert!(!and!(filter1.clone(), filter2.clone(),).matches(&doc));
+        assert!(or!(filter1.clone(), filter2.clone(),).matches(&doc));
+        assert!(and!(filter1, FilterQuery::Not(Box::new(filter2)),).matches(&doc));
+    }
+
+    #[test]
+    fn should_not_match_negated_missing_fields() {
+        let mut where_filter = HashMap::new();
+        where_filter.insert("x".to_string(), OperationFilter::EqualTo(1.into()));
+        let filter = FilterQuery::Where(where_filter);
+        let not = FilterQuery::Not(Box::new(filter.clone()));
+        let missing = serde_json::json!({"y": 1});
+        assert!(!filter.matches(&missing));
+        assert!(!not.matches(&missing));
+        assert!(not.matches(&serde_json::json!({"x": 2})));
+        assert!(!not.matches(&serde_json::json!({"x": 1})));
+
+        // unknown or true is true, unknown and false is false
+        let mut where_y = HashMap::new();
+        where_y.insert("y".to_string(), OperationFilter::EqualTo(1.into()));
+        let y = FilterQuery::Where(where_y);
+        assert!(or!(filter.clone(), y.clone(),).matches(&missing));
+        let not_and = FilterQuery::Not(Box::new(and!(filter, FilterQuery::Not(Box::new(y)),)));
+        assert!(not_and.matches(&missing));
+    }
+
+    #[test]
+    fn should_round_trip_filters() {
+        let json = r#"{"or":[{"and":[{"where":{"id":{"in":["a",1,2.5]}}},{"where":{"id2":{"equalTo":true}}}]},{"not":{"where":{"id3":{"lessThan":0.1}}}}]}"#;
+        let filter: FilterQuery = serde_json::from_str(json).unwrap();
+        assert_eq!(serde_json::to_string(&filter).unwrap(), json);
+        assert!(filter.matches(&serde_json::json!({"id3": 1})));
+    }
 }
@@ -0,0 +1,172 @@
+use rand::Rng;
+use std::time::Duration;
+
+/// Whether repeating a request can change the outcome on the node
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Idempotency {
+    /// Request can be repeated safely, e.g. loads, queries and genesis posts
+    Idempotent,
+    /// Request may not be repeated once received by the node, e.g. commit posts
+    NonIdempotent,
+}
+
+/// Policy for retrying failed requests against a remote ceramic
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between attempts
+    pub max_backoff: Duration,
+    /// Factor the delay grows by after each attempt
+    pub multiplier: f64,
+    /// Fraction of each delay that is randomized, between 0 and 1
+    pub jitter: f64,
+    /// Response statuses that are retried
+    pub retry_statuses: Vec<u16>,
+    /// Retry non idempotent requests on retryable statuses and errors. When false, these are
+    /// only retried if the connection could not be established, since otherwise the node may
+    /// have already applied them.
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: 0.2,
+            retry_statuses: vec![429, 502, 503, 504],
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy that never retries
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Delay before the given retry, where the first retry is 1
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exp = self.multiplier.powi(retry.saturating_sub(1) as i32);
+        let delay = self
+            .initial_backoff
+            .mul_f64(exp)
+            .min(self.max_backoff)
+            .as_secs_f64();
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let delay = if jitter > 0.0 {
+            delay * rand::thread_rng().gen_range(1.0 - jitter..=1.0)
+        } else {
+            delay
+        };
+        Duration::from_secs_f64(delay)
+    }
+
+    pub(crate) fn retry_status(&self, status: u16, idempotency: Idempotency) -> bool {
+        self.allows(idempotency) && self.retry_statuses.contains(&status)
+    }
+
+    pub(crate) fn retry_error(&self, err: &reqwest::Error, idempotency: Idempotency) -> bool {
+        err.is_connect() || (self.allows(idempotency) && (err.is_timeout() || err.is_request()))
+    }
+
+    fn allows(&self, idempotency: Idempotency) -> bool {
+        idempotency == Idempotency::Idempotent || self.retry_non_idempotent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_backoff_exponentially() {
+        let policy = RetryPolicy {
+            jitter: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(10), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn should_jitter_within_bounds() {
+        let policy = RetryPolicy::default();
+        for _ in 0..100 {
+            let delay = policy.backoff(2);
+            assert!(delay >= Duration::from_millis(160));
+            assert!(delay <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn should_only_retry_idempotent_statuses() {
+        let policy = RetryPolicy::default();
+        assert!(policy.retry_status(503, Idempotency::Idempotent));
+        assert!(!policy.retry_status(500, Idempotency::Idempotent));
+        assert!(!policy.retry_status(503, Idempotency::NonIdempotent));
+        let policy = RetryPolicy {
+            retry_non_idempotent: true,
+            ..Default::default()
+        };
+        assert!(policy.retry_status(503, Idempotency::NonIdempotent));
+    }
+
+    #[cfg(feature = "mock")]
+    mod mock {
+        use crate::mock::tests::{setup, Ball};
+        use crate::remote::RetryPolicy;
+        use crate::ModelAccountRelation;
+        use serde_json::json;
+        use std::time::Duration;
+
+        #[tokio::test]
+        async fn should_retry_idempotent_requests() {
+            let (mock, ceramic, model) = setup(ModelAccountRelation::List).await;
+            let ceramic = ceramic.with_retry_policy(RetryPolicy {
+                initial_backoff: Duration::from_millis(1),
+                jitter: 0.0,
+                ..Default::default()
+            });
+            let stream_id = ceramic
+                .create_list_instance(&model, &Ball { radius: 1, red: 2 })
+                .await
+                .unwrap();
+            let requests = |method: &str, path: &str| {
+                mock.requests()
+                    .iter()
+                    .filter(|r| r.method == method && r.path.starts_with(path))
+                    .count()
+            };
+
+            let loads = requests("GET", "/api/v0/streams/");
+            mock.fail_next("/api/v0/streams/", 503, 2);
+            let ball: Ball = ceramic.get_as(&stream_id).await.unwrap();
+            assert_eq!(ball, Ball { radius: 1, red: 2 });
+            assert_eq!(requests("GET", "/api/v0/streams/"), loads + 3);
+
+            mock.fail_next("/api/v0/streams/", 500, 1);
+            assert!(ceramic.get(&stream_id).await.is_err());
+            assert_eq!(requests("GET", "/api/v0/streams/"), loads + 4);
+
+            // the commit post fails once and is not repeated, since the node may have applied it
+            let patch = json_patch::diff(&json!({"red": 2}), &json!({"red": 3}));
+            mock.fail_next("/api/v0/commits", 503, 1);
+            assert!(ceramic.update(&model, &stream_id, patch).await.is_err());
+            assert_eq!(requests("POST", "/api/v0/commits"), 1);
+            let ball: Ball = ceramic.get_as(&stream_id).await.unwrap();
+            assert_eq!(ball, Ball { radius: 1, red: 2 });
+        }
+    }
+}
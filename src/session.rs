@@ -0,0 +1,394 @@
+use crate::{Error, Result};
+use base64::Engine;
+use ceramic_event::{Base64String, Cid, DidDocument, Jws, Signer};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use ssi::jwk::Algorithm;
+
+const B64: base64::engine::GeneralPurpose = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+/// CIDv1, dag-cbor codec, sha2-256 multihash of 32 bytes
+const DAG_CBOR_SHA256_PREFIX: [u8; 4] = [0x01, 0x71, 0x12, 0x20];
+/// CIDv1, dag-jose codec, sha2-256 multihash of 32 bytes
+const DAG_JOSE_SHA256_PREFIX: [u8; 5] = [0x01, 0x85, 0x01, 0x12, 0x20];
+
+#[derive(Deserialize)]
+struct CacaoBlock {
+    p: CacaoPayload,
+}
+
+#[derive(Deserialize)]
+struct CacaoPayload {
+    iss: String,
+    #[serde(default)]
+    aud: String,
+    #[serde(default)]
+    resources: Vec<String>,
+    #[serde(default)]
+    exp: Option<String>,
+}
+
+/// Chain agnostic capability object (CACAO), granting a session key the right to write on
+/// behalf of an account, e.g. as obtained through Sign-In-With-Ethereum
+#[derive(Clone, Debug)]
+pub struct Cacao {
+    block: Vec<u8>,
+    cid: Cid,
+    issuer: String,
+    audience: String,
+    resources: Vec<String>,
+    expiration: Option<String>,
+}
+
+impl Cacao {
+    /// Create a capability from its dag-cbor encoded block
+    pub fn from_block(block: Vec<u8>) -> Result<Self> {
+        let decoded: CacaoBlock = ciborium::de::from_reader(block.as_slice())
+            .map_err(|e| Error::Encoding(anyhow::anyhow!("Invalid cacao block: {}", e)))?;
+        let cid = dag_cbor_cid(&block)?;
+        Ok(Self {
+            block,
+            cid,
+            issuer: decoded.p.iss,
+            audience: decoded.p.aud,
+            resources: decoded.p.resources,
+            expiration: decoded.p.exp,
+        })
+    }
+
+    /// Dag-cbor encoded block of this capability
+    pub fn block(&self) -> &[u8] {
+        &self.block
+    }
+
+    /// CID of the capability block
+    pub fn cid(&self) -> &Cid {
+        &self.cid
+    }
+
+    /// DID of the account that issued this capability, e.g. `did:pkh:eip155:1:0x...`
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    /// DID of the session key this capability was granted to
+    pub fn audience(&self) -> &str {
+        &self.audience
+    }
+
+    /// Resources this capability grants access to, e.g. `ceramic://*?model=<model id>`
+    pub fn resources(&self) -> &[String] {
+        &self.resources
+    }
+
+    /// Time this capability expires at, as an RFC 3339 timestamp
+    pub fn expiration(&self) -> Option<&str> {
+        self.expiration.as_deref()
+    }
+}
+
+/// Session that signs with a session key on behalf of the issuer of a CACAO. Used as a signer,
+/// the session identifies as the issuer so that streams are controlled by the issuing account.
+#[derive(Clone, Debug)]
+pub struct DidSession<S: Signer> {
+    signer: S,
+    issuer: DidDocument,
+    cacao: Cacao,
+}
+
+impl<S: Signer> DidSession<S> {
+    /// Create a new session from a session key signer and the capability granted to it
+    pub fn new(signer: S, cacao: Cacao) -> Self {
+        Self {
+            issuer: DidDocument::new(cacao.issuer()),
+            signer,
+            cacao,
+        }
+    }
+
+    /// Signer for the session key
+    pub fn session_signer(&self) -> &S {
+        &self.signer
+    }
+
+    /// Capability granted to the session key
+    pub fn cacao(&self) -> &Cacao {
+        &self.cacao
+    }
+
+    pub(crate) fn capability(&self) -> Capability {
+        let did = &self.signer.id().id;
+        let kid = match did.strip_prefix("did:key:") {
+            Some(key) => format!("{}#{}", did, key),
+            None => did.clone(),
+        };
+        Capability {
+            cacao: self.cacao.clone(),
+            kid,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: Signer + Send + Sync> Signer for DidSession<S> {
+    fn algorithm(&self) -> Algorithm {
+        self.signer.algorithm()
+    }
+
+    fn id(&self) -> &DidDocument {
+        &self.issuer
+    }
+
+    async fn sign(&self, bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.signer.sign(bytes).await
+    }
+}
+
+/// Capability attached to commits created by a client using a `DidSession`
+#[derive(Clone, Debug)]
+pub(crate) struct Capability {
+    cacao: Cacao,
+    kid: String,
+}
+
+impl Capability {
+    pub(crate) fn cacao_block(&self) -> Base64String {
+        Base64String::from(self.cacao.block.clone())
+    }
+
+    /// Re-sign a commit jws with a protected header referencing the session key and capability
+    pub(crate) async fn attach<S: Signer>(&self, signer: &S, jws: Jws) -> Result<Jws> {
+        let invalid = || Error::Signing(anyhow::anyhow!("Invalid jws, no signatures"));
+        let mut jws = serde_json::to_value(&jws)?;
+        let payload = jws
+            .get("payload")
+            .and_then(Value::as_str)
+            .ok_or_else(invalid)?
+            .to_string();
+        let signature = jws
+            .get_mut("signatures")
+            .and_then(|sigs| sigs.get_mut(0))
+            .ok_or_else(invalid)?;
+        let protected = signature
+            .get("protected")
+            .and_then(Value::as_str)
+            .ok_or_else(invalid)?;
+        let protected = B64
+            .decode(protected)
+            .map_err(|e| Error::Encoding(e.into()))?;
+        let mut header: serde_json::Map<String, Value> = serde_json::from_slice(&protected)?;
+        header.insert("kid".to_string(), Value::String(self.kid.clone()));
+        header.insert(
+            "cap".to_string(),
+            Value::String(format!("ipfs://{}", self.cacao.cid)),
+        );
+        let protected = B64.encode(serde_json::to_vec(&header)?);
+        let sig = signer
+            .sign(format!("{}.{}", protected, payload).as_bytes())
+            .await
+            .map_err(Error::Signing)?;
+        signature["protected"] = Value::String(protected);
+        signature["signature"] = Value::String(B64.encode(sig));
+        Ok(serde_json::from_value(jws)?)
+    }
+}
+
+/// Encode a general json serialized jws as a dag-jose block, with keys in canonical order
+pub(crate) fn dag_jose_block(jws: &Value) -> Result<Vec<u8>> {
+    use ciborium::value::Value as Cbor;
+    let invalid = || Error::Encoding(anyhow::anyhow!("Invalid jws"));
+    let bytes = |value: Option<&Value>| -> Result<Cbor> {
+        let value = value.and_then(Value::as_str).ok_or_else(invalid)?;
+        let value = B64
+            .decode(value.trim_end_matches('='))
+            .map_err(|e| Error::Encoding(e.into()))?;
+        Ok(Cbor::Bytes(value))
+    };
+    let signatures = jws
+        .get("signatures")
+        .and_then(Value::as_array)
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|sig| {
+            Ok(Cbor::Map(vec![
+                (Cbor::Text("protected".into()), bytes(sig.get("protected"))?),
+                (Cbor::Text("signature".into()), bytes(sig.get("signature"))?),
+            ]))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let block = Cbor::Map(vec![
+        (Cbor::Text("payload".into()), bytes(jws.get("payload"))?),
+        (Cbor::Text("signatures".into()), Cbor::Array(signatures)),
+    ]);
+    let mut encoded = Vec::new();
+    ciborium::ser::into_writer(&block, &mut encoded)
+        .map_err(|e| Error::Encoding(anyhow::anyhow!("Invalid jws: {}", e)))?;
+    Ok(encoded)
+}
+
+/// CID of the dag-jose block of a jws
+pub(crate) fn jws_cid(jws: &Jws) -> Result<Cid> {
+    dag_jose_cid(&dag_jose_block(&serde_json::to_value(jws)?)?)
+}
+
+/// CID of a dag-cbor encoded block
+pub(crate) fn dag_cbor_cid(block: &[u8]) -> Result<Cid> {
+    block_cid(&DAG_CBOR_SHA256_PREFIX, block)
+}
+
+/// CID of a dag-jose encoded block
+pub(crate) fn dag_jose_cid(block: &[u8]) -> Result<Cid> {
+    block_cid(&DAG_JOSE_SHA256_PREFIX, block)
+}
+
+fn block_cid(prefix: &[u8], block: &[u8]) -> Result<Cid> {
+    let mut cid = prefix.to_vec();
+    cid.extend_from_slice(&Sha256::digest(block));
+    Cid::try_from(cid.as_slice()).map_err(|e| Error::Encoding(e.into()))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{CeramicHttpClient, GetRootSchema, ModelAccountRelation, ModelDefinition};
+    use ceramic_event::JwkSigner;
+    use schemars::JsonSchema;
+    use serde::Serialize;
+    use serde_json::json;
+
+    /// Session key used by tests
+    pub(crate) const DID: &str = "did:key:z6Mkk3rtfoKDMMG4zyarNGwCQs44GSQ49pcYKQspHJPXSnVw";
+    const KEY: &str = "810d51e02cb63066b7d2d2ec67e05e18c29b938412050bdd3c04d878d8001f3c";
+
+    pub(crate) async fn signer() -> JwkSigner {
+        JwkSigner::new(DidDocument::new(DID), KEY).await.unwrap()
+    }
+
+    #[derive(JsonSchema, Serialize)]
+    struct Ball {
+        red: i32,
+    }
+
+    impl GetRootSchema for Ball {}
+
+    fn protected_header(jws: &Jws) -> Value {
+        let jws = serde_json::to_value(jws).unwrap();
+        let protected = jws["signatures"][0]["protected"].as_str().unwrap();
+        serde_json::from_slice(&B64.decode(protected).unwrap()).unwrap()
+    }
+
+    /// Capability issued by an ethereum account to the `audience` session key for all streams
+    pub(crate) fn cacao(audience: &str, expiration: &str) -> Cacao {
+        use ciborium::value::Value as Cbor;
+        let text = |s: &str| Cbor::Text(s.to_string());
+        let block = Cbor::Map(vec![
+            (text("h"), Cbor::Map(vec![(text("t"), text("eip4361"))])),
+            (
+                text("p"),
+                Cbor::Map(vec![
+                    (text("aud"), text(audience)),
+                    (text("exp"), text(expiration)),
+                    (
+                        text("iss"),
+                        text("did:pkh:eip155:1:0x3794d4f077c08d925ff8ff820006b7353299b200"),
+                    ),
+                    (text("resources"), Cbor::Array(vec![text("ceramic://*")])),
+                ]),
+            ),
+            (
+                text("s"),
+                Cbor::Map(vec![
+                    (text("s"), Cbor::Bytes(vec![0; 65])),
+                    (text("t"), text("eip191")),
+                ]),
+            ),
+        ]);
+        let mut encoded = Vec::new();
+        ciborium::ser::into_writer(&block, &mut encoded).unwrap();
+        Cacao::from_block(encoded).unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_attach_capability_to_requests() {
+        let cacao = cacao(DID, "2100-01-01T00:00:00.000Z");
+        let issuer = cacao.issuer().to_string();
+        let cap = format!("ipfs://{}", cacao.cid());
+        let cacao_block = Some(Base64String::from(cacao.block().to_vec()));
+        let cli = CeramicHttpClient::new_with_session(DidSession::new(signer().await, cacao));
+        let kid = format!("{}#{}", DID, DID.trim_start_matches("did:key:"));
+
+        let model = ModelDefinition::new::<Ball>("Ball", ModelAccountRelation::List).unwrap();
+        let (req, model) = cli.create_model_request(&model).await.unwrap();
+        let jws = req.block.jws.as_ref().unwrap();
+        assert_eq!(req.block.cacao_block, cacao_block);
+        assert_eq!(req.block.header.controllers, vec![issuer.clone()]);
+        assert_eq!(model.cid, jws_cid(jws).unwrap());
+
+        let (req, stream_id) = cli
+            .create_list_instance_request(&model, json!({"red": 1}))
+            .await
+            .unwrap();
+        let jws = req.block.jws.as_ref().unwrap();
+        let header = protected_header(jws);
+        assert_eq!(header["kid"], kid);
+        assert_eq!(header["cap"], cap);
+        assert_eq!(req.block.cacao_block, cacao_block);
+        assert_eq!(req.block.header.controllers, vec![issuer.clone()]);
+        assert_eq!(stream_id.cid, jws_cid(jws).unwrap());
+
+        let get = serde_json::from_value(json!({
+            "streamId": stream_id.to_string(),
+            "state": {
+                "content": {"red": 1},
+                "log": [{"cid": stream_id.cid.to_string()}],
+                "metadata": {"controllers": [issuer], "model": model.to_string()},
+            },
+        }))
+        .unwrap();
+        let req = cli
+            .create_replace_request(&model, &get, json!({"red": 2}))
+            .await
+            .unwrap();
+        let header = protected_header(req.block.jws.as_ref().unwrap());
+        assert_eq!(header["kid"], kid);
+        assert_eq!(header["cap"], cap);
+        assert_eq!(req.block.cacao_block, cacao_block);
+        assert_eq!(req.block.header.controllers, vec![issuer.clone()]);
+
+        // deterministic ids are those of the issuer, not of the session key
+        let owner = JwkSigner::new(DidDocument::new(&issuer), KEY)
+            .await
+            .unwrap();
+        assert_eq!(
+            cli.single_instance_stream_id(&model).unwrap(),
+            CeramicHttpClient::new(owner)
+                .single_instance_stream_id(&model)
+                .unwrap()
+        );
+        assert_ne!(
+            cli.single_instance_stream_id(&model).unwrap(),
+            CeramicHttpClient::new(signer().await)
+                .single_instance_stream_id(&model)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn should_encode_dag_jose_block() {
+        let jws = serde_json::json!({
+            "link": "ignored",
+            "payload": "AQ",
+            "signatures": [{"protected": "Ag", "signature": "Aw"}],
+        });
+        let mut expected = vec![0xa2, 0x67];
+        expected.extend(b"payload");
+        expected.extend([0x41, 0x01, 0x6a]);
+        expected.extend(b"signatures");
+        expected.extend([0x81, 0xa2, 0x69]);
+        expected.extend(b"protected");
+        expected.extend([0x41, 0x02, 0x69]);
+        expected.extend(b"signature");
+        expected.extend([0x41, 0x03]);
+        assert_eq!(dag_jose_block(&jws).unwrap(), expected);
+    }
+}
@@ -0,0 +1,850 @@
+use crate::{session, Error, Result};
+use base64::Engine;
+use ceramic_event::{Cid, StreamId};
+use ciborium::value::Value as Cbor;
+use serde::Deserialize;
+use serde_json::Value;
+use ssi::jwk::Algorithm;
+use std::str::FromStr;
+
+const B64: base64::engine::GeneralPurpose = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+/// Multicodec prefix of an ed25519 public key
+const ED25519_PUB: [u8; 2] = [0xed, 0x01];
+
+/// Reason a commit failed verification
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerificationFailure {
+    /// A commit other than the genesis is neither signed nor an anchor commit
+    Unsigned,
+    /// The commit is malformed, or its content does not hash to its cid
+    InvalidCommit(String),
+    /// The jws signature does not verify against the key of its signer
+    InvalidSignature(String),
+    /// The commit was signed by a DID other than the controller of the stream
+    UnauthorizedSigner {
+        /// DID that signed the commit
+        signer: String,
+        /// Controller of the stream
+        controller: String,
+    },
+    /// The controller reported by the node differs from the controller in the genesis commit
+    ControllerMismatch {
+        /// Controller in the genesis commit
+        expected: String,
+        /// Controller reported by the node
+        actual: String,
+    },
+    /// The capability a session key signed the commit with is missing or malformed, was not
+    /// granted to the session key, does not cover the stream, or has expired
+    InvalidCapability(String),
+    /// The signer uses a key type that cannot be verified
+    Unsupported(String),
+    /// The commit was signed by a session key with a valid signature and a well formed
+    /// capability, but the signature of the capability (CACAO) by the controller cannot be
+    /// verified, so the commit is not known to be authorized by the controller
+    UnsupportedCapability(String),
+    /// The commit does not link to the previous commit of the log
+    BrokenChain {
+        /// Cid of the previous commit
+        expected: String,
+        /// Cid the commit links to
+        actual: Option<String>,
+    },
+}
+
+/// Commit that failed verification
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitFailure {
+    /// Cid of the commit
+    pub cid: String,
+    /// Reason the commit failed
+    pub failure: VerificationFailure,
+}
+
+/// Result of verifying the log of a stream. Anchor proofs are not verified. Commits signed by a
+/// session key fail with `UnsupportedCapability`, since the signature of their capability (CACAO)
+/// cannot be verified. The expiry of a capability is only checked for commits that are not yet
+/// anchored.
+#[derive(Clone, Debug)]
+pub struct Verification {
+    /// Stream that was verified
+    pub stream_id: StreamId,
+    /// Commits that failed verification, in log order
+    pub failures: Vec<CommitFailure>,
+}
+
+impl Verification {
+    /// Whether all commits of the stream passed verification
+    pub fn is_valid(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Convert to an error if any commit failed verification
+    pub fn into_result(self) -> Result<Self> {
+        if self.is_valid() {
+            Ok(self)
+        } else {
+            Err(Error::Verification(Box::new(self)))
+        }
+    }
+}
+
+/// Commit as returned by the commits endpoint
+#[derive(Deserialize)]
+pub(crate) struct RawCommit {
+    pub(crate) cid: String,
+    #[serde(default)]
+    value: Value,
+}
+
+/// Commits as returned by the commits endpoint
+#[derive(Deserialize)]
+pub(crate) struct RawCommits {
+    pub(crate) commits: Vec<RawCommit>,
+}
+
+/// Documents as returned by the query endpoint, along with the log of each
+#[derive(Deserialize)]
+pub(crate) struct RawQueryResponse {
+    pub(crate) edges: Vec<RawEdge>,
+}
+
+/// Document returned by the query endpoint
+#[derive(Deserialize)]
+pub(crate) struct RawEdge {
+    pub(crate) node: RawNode,
+}
+
+/// Log of a document returned by the query endpoint
+#[derive(Deserialize)]
+pub(crate) struct RawNode {
+    pub(crate) log: Vec<RawCommit>,
+}
+
+/// Verify the log of a stream up to and including the `tip` of a loaded state, which may be an
+/// earlier commit than the latest one
+pub(crate) fn verify_commits_until(
+    stream_id: &StreamId,
+    reported: Option<&str>,
+    commits: &[RawCommit],
+    tip: &Cid,
+) -> Verification {
+    match commits
+        .iter()
+        .position(|c| parse_cid(&c.cid).ok().as_ref() == Some(tip))
+    {
+        Some(pos) => verify_commits(stream_id, reported, &commits[..=pos]),
+        None => Verification {
+            stream_id: stream_id.clone(),
+            failures: vec![CommitFailure {
+                cid: tip.to_string(),
+                failure: VerificationFailure::InvalidCommit(
+                    "commit is not in the log of the stream".to_string(),
+                ),
+            }],
+        },
+    }
+}
+
+/// Verify the log of a stream against the controller in its genesis commit, which must match
+/// the `reported` controller when the node reported one
+pub(crate) fn verify_commits(
+    stream_id: &StreamId,
+    reported: Option<&str>,
+    commits: &[RawCommit],
+) -> Verification {
+    let mut failures = Vec::new();
+    let mut fail = |cid: &str, failure| {
+        failures.push(CommitFailure {
+            cid: cid.to_string(),
+            failure,
+        })
+    };
+    let mut controller = None;
+    let mut resource = Resource {
+        stream_id: stream_id.to_string(),
+        model: None,
+    };
+    let mut prev: Option<&RawCommit> = None;
+    for (i, commit) in commits.iter().enumerate() {
+        let block = match verify_block(commit) {
+            Ok(block) => block,
+            Err(failure) => {
+                fail(&commit.cid, failure);
+                prev = Some(commit);
+                continue;
+            }
+        };
+        match prev {
+            None => {
+                if parse_cid(&commit.cid).ok() != Some(stream_id.cid) {
+                    fail(
+                        &commit.cid,
+                        VerificationFailure::BrokenChain {
+                            expected: stream_id.cid.to_string(),
+                            actual: Some(commit.cid.clone()),
+                        },
+                    );
+                }
+                controller = genesis_controller(&block);
+                resource.model = genesis_model(&block);
+                match (&controller, reported) {
+                    (None, _) => fail(
+                        &commit.cid,
+                        VerificationFailure::InvalidCommit("genesis has no controller".to_string()),
+                    ),
+                    (Some(expected), Some(actual)) if expected != actual => fail(
+                        &commit.cid,
+                        VerificationFailure::ControllerMismatch {
+                            expected: expected.clone(),
+                            actual: actual.to_string(),
+                        },
+                    ),
+                    _ => {}
+                }
+            }
+            Some(prev) => {
+                let actual = link(&block, "prev");
+                if actual.as_ref().and_then(|a| parse_cid(a).ok()) != parse_cid(&prev.cid).ok() {
+                    fail(
+                        &commit.cid,
+                        VerificationFailure::BrokenChain {
+                            expected: prev.cid.clone(),
+                            actual,
+                        },
+                    );
+                }
+            }
+        }
+        if let Some(jws) = commit.value.get("jws") {
+            let anchored = commits[i + 1..]
+                .iter()
+                .any(|c| c.value.get("proof").is_some());
+            let cacao_block = commit.value.get("cacaoBlock").and_then(Value::as_str);
+            let signed = Signed {
+                jws,
+                cacao_block,
+                anchored,
+            };
+            if let Err(failure) = verify_signature(&signed, controller.as_deref(), &resource) {
+                fail(&commit.cid, failure);
+            }
+        }
+        prev = Some(commit);
+    }
+    Verification {
+        stream_id: stream_id.clone(),
+        failures,
+    }
+}
+
+/// Stream that capabilities must grant access to
+struct Resource {
+    stream_id: String,
+    model: Option<String>,
+}
+
+/// Signed commit, along with the capability it was signed with
+struct Signed<'a> {
+    jws: &'a Value,
+    cacao_block: Option<&'a str>,
+    /// Whether a later commit anchors the commit
+    anchored: bool,
+}
+
+/// Content of a commit, the linked block for signed commits or the value itself otherwise
+enum Block {
+    Cbor(Cbor),
+    Json(Value),
+}
+
+fn verify_block(commit: &RawCommit) -> Result<Block, VerificationFailure> {
+    let invalid = |e: &dyn std::fmt::Display| VerificationFailure::InvalidCommit(e.to_string());
+    let cid = parse_cid(&commit.cid).map_err(|e| invalid(&e))?;
+    let jws = match commit.value.get("jws") {
+        Some(jws) => jws,
+        None if commit.value.get("header").is_some() => {
+            let block = unsigned_genesis_block(&commit.value).map_err(|e| invalid(&e))?;
+            if session::dag_cbor_cid(&block).map_err(|e| invalid(&e))? != cid {
+                return Err(invalid(&"genesis does not hash to the commit cid"));
+            }
+            return Ok(Block::Json(commit.value.clone()));
+        }
+        None if commit.value.get("proof").is_some() => {
+            return Ok(Block::Json(commit.value.clone()))
+        }
+        None => return Err(VerificationFailure::Unsigned),
+    };
+    let jws_block = session::dag_jose_block(jws).map_err(|e| invalid(&e))?;
+    if session::dag_jose_cid(&jws_block).map_err(|e| invalid(&e))? != cid {
+        return Err(invalid(&"jws does not hash to the commit cid"));
+    }
+    let linked_block = commit
+        .value
+        .get("linkedBlock")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid(&"missing linked block"))?;
+    let linked_block = decode_base64(linked_block).map_err(|e| invalid(&e))?;
+    let expected = session::dag_cbor_cid(&linked_block).map_err(|e| invalid(&e))?;
+    let payload = jws
+        .get("payload")
+        .and_then(Value::as_str)
+        .and_then(|payload| decode_base64(payload).ok())
+        .and_then(|payload| Cid::try_from(payload.as_slice()).ok());
+    if payload != Some(expected) {
+        return Err(invalid(&"jws payload does not link to the linked block"));
+    }
+    let block = ciborium::de::from_reader(linked_block.as_slice()).map_err(|e| invalid(&e))?;
+    Ok(Block::Cbor(block))
+}
+
+fn verify_signature(
+    signed: &Signed<'_>,
+    controller: Option<&str>,
+    resource: &Resource,
+) -> Result<(), VerificationFailure> {
+    let jws = signed.jws;
+    let invalid = |e: &dyn std::fmt::Display| VerificationFailure::InvalidSignature(e.to_string());
+    let signature = jws
+        .get("signatures")
+        .and_then(|sigs| sigs.get(0))
+        .ok_or_else(|| invalid(&"no signatures"))?;
+    let protected = signature
+        .get("protected")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid(&"missing protected header"))?;
+    let header: Value = decode_base64(protected)
+        .ok()
+        .and_then(|h| serde_json::from_slice(&h).ok())
+        .ok_or_else(|| invalid(&"invalid protected header"))?;
+    let kid = header
+        .get("kid")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid(&"missing kid"))?;
+    let signer = kid.split('#').next().unwrap_or(kid);
+    // Commits signed by a session key are written on behalf of the issuer of its capability
+    let cap = header.get("cap").and_then(Value::as_str);
+    let issuer = match cap {
+        Some(cap) => verify_capability(cap, signed, signer, resource)?,
+        None => signer.to_string(),
+    };
+    if let Some(controller) = controller {
+        if issuer != controller {
+            return Err(VerificationFailure::UnauthorizedSigner {
+                signer: issuer,
+                controller: controller.to_string(),
+            });
+        }
+    }
+    if header.get("alg").and_then(Value::as_str) != Some("EdDSA") {
+        return Err(VerificationFailure::Unsupported(format!(
+            "algorithm of {}",
+            signer
+        )));
+    }
+    let key = signer
+        .strip_prefix("did:key:")
+        .and_then(|key| multibase::decode(key).ok())
+        .and_then(|(_, key)| key.strip_prefix(&ED25519_PUB).map(<[u8]>::to_vec))
+        .ok_or_else(|| VerificationFailure::Unsupported(format!("key of {}", signer)))?;
+    let key = ssi::jwk::ed25519_parse(&key).map_err(|e| invalid(&e))?;
+    let payload = jws
+        .get("payload")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid(&"missing payload"))?;
+    let sig = signature
+        .get("signature")
+        .and_then(Value::as_str)
+        .map(decode_base64)
+        .transpose()
+        .map_err(|e| invalid(&e))?
+        .ok_or_else(|| invalid(&"missing signature"))?;
+    ssi::jws::verify_bytes(
+        Algorithm::EdDSA,
+        format!("{}.{}", protected, payload).as_bytes(),
+        &key,
+        &sig,
+    )
+    .map_err(|e| invalid(&e))?;
+    match cap {
+        Some(cap) => Err(VerificationFailure::UnsupportedCapability(format!(
+            "signature of capability {} by {}",
+            cap, issuer
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Re-encode an unsigned genesis commit, as returned in json by the node, to its dag-cbor block.
+/// The model and unique values of the header are bytes, returned as base64 or, for the model, as
+/// a stream id.
+fn unsigned_genesis_block(value: &Value) -> anyhow::Result<Vec<u8>> {
+    let entries = value
+        .as_object()
+        .ok_or_else(|| anyhow::anyhow!("genesis is not an object"))?;
+    let mut block = Vec::new();
+    for (key, value) in entries {
+        let value = match (key.as_str(), value) {
+            ("header", Value::Object(header)) => {
+                let mut entries = Vec::new();
+                for (key, value) in header {
+                    let value = match (key.as_str(), value) {
+                        ("model", Value::String(model)) => {
+                            Cbor::Bytes(match StreamId::from_str(model) {
+                                Ok(model) => model.to_vec()?,
+                                Err(_) => decode_base64(model)?,
+                            })
+                        }
+                        ("unique", Value::String(unique)) => Cbor::Bytes(decode_base64(unique)?),
+                        _ => json_to_cbor(value),
+                    };
+                    entries.push((key.clone(), value));
+                }
+                canonical_map(entries)
+            }
+            _ => json_to_cbor(value),
+        };
+        block.push((key.clone(), value));
+    }
+    let mut encoded = Vec::new();
+    ciborium::ser::into_writer(&canonical_map(block), &mut encoded)?;
+    Ok(encoded)
+}
+
+fn json_to_cbor(value: &Value) -> Cbor {
+    match value {
+        Value::Null => Cbor::Null,
+        Value::Bool(b) => Cbor::Bool(*b),
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(n), _) => Cbor::Integer(n.into()),
+            (_, Some(n)) => Cbor::Integer(n.into()),
+            _ => Cbor::Float(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => Cbor::Text(s.clone()),
+        Value::Array(values) => Cbor::Array(values.iter().map(json_to_cbor).collect()),
+        Value::Object(entries) => canonical_map(
+            entries
+                .iter()
+                .map(|(k, v)| (k.clone(), json_to_cbor(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// Map with keys in dag-cbor canonical order, shortest first
+fn canonical_map(mut entries: Vec<(String, Cbor)>) -> Cbor {
+    entries.sort_by(|(a, _), (b, _)| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+    Cbor::Map(
+        entries
+            .into_iter()
+            .map(|(k, v)| (Cbor::Text(k), v))
+            .collect(),
+    )
+}
+
+/// Verify the capability a session key signed a commit with, returning the DID of its issuer
+fn verify_capability(
+    cap: &str,
+    signed: &Signed<'_>,
+    signer: &str,
+    resource: &Resource,
+) -> Result<String, VerificationFailure> {
+    let invalid = |e: &dyn std::fmt::Display| VerificationFailure::InvalidCapability(e.to_string());
+    let block = signed
+        .cacao_block
+        .ok_or_else(|| invalid(&"missing cacao block"))?;
+    let block = decode_base64(block).map_err(|e| invalid(&e))?;
+    let cacao = session::Cacao::from_block(block).map_err(|e| invalid(&e))?;
+    let cid = cap
+        .strip_prefix("ipfs://")
+        .and_then(|cid| parse_cid(cid).ok());
+    if cid.as_ref() != Some(cacao.cid()) {
+        return Err(invalid(&format!("cacao block is not capability {}", cap)));
+    }
+    if cacao.audience() != signer {
+        return Err(invalid(&format!(
+            "capability was granted to {} rather than {}",
+            cacao.audience(),
+            signer
+        )));
+    }
+    let granted = cacao.resources().iter().any(|r| {
+        r == "ceramic://*"
+            || *r == format!("ceramic://{}", resource.stream_id)
+            || resource
+                .model
+                .as_ref()
+                .is_some_and(|model| *r == format!("ceramic://*?model={}", model))
+    });
+    if !granted {
+        return Err(invalid(&format!(
+            "capability does not grant access to {}",
+            resource.stream_id
+        )));
+    }
+    if let (false, Some(expiration)) = (signed.anchored, cacao.expiration()) {
+        let expires_at = unix_time(expiration)
+            .ok_or_else(|| invalid(&format!("invalid expiration {}", expiration)))?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+        if expires_at < now {
+            return Err(invalid(&format!("capability expired at {}", expiration)));
+        }
+    }
+    Ok(cacao.issuer().to_string())
+}
+
+/// Seconds since the unix epoch of an RFC 3339 timestamp
+fn unix_time(timestamp: &str) -> Option<i64> {
+    let (date, time) = timestamp.split_once(['T', 't'])?;
+    let mut date = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let (time, offset) = time.split_at(time.find(['Z', 'z', '+', '-'])?);
+    let offset = match offset.split_once(':') {
+        Some((hours, minutes)) => {
+            let minutes = hours[1..].parse::<i64>().ok()? * 60 + minutes.parse::<i64>().ok()?;
+            if hours.starts_with('-') {
+                -minutes * 60
+            } else {
+                minutes * 60
+            }
+        }
+        None => 0,
+    };
+    let mut time = time.splitn(3, ':');
+    let hours = time.next()?.parse::<i64>().ok()?;
+    let minutes = time.next()?.parse::<i64>().ok()?;
+    let seconds = time.next()?.split('.').next()?.parse::<i64>().ok()?;
+    // Days since the epoch of the civil date, see
+    // https://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+    Some(days * 86400 + hours * 3600 + minutes * 60 + seconds - offset)
+}
+
+/// Model of a stream, as a stream id
+fn genesis_model(block: &Block) -> Option<String> {
+    let model = match block {
+        Block::Cbor(block) => match field(field(block, "header")?, "model")? {
+            Cbor::Bytes(model) => model.clone(),
+            _ => return None,
+        },
+        Block::Json(value) => {
+            let model = value.pointer("/header/model").and_then(Value::as_str)?;
+            match StreamId::from_str(model) {
+                Ok(model) => return Some(model.to_string()),
+                Err(_) => decode_base64(model).ok()?,
+            }
+        }
+    };
+    Some(multibase::encode(multibase::Base::Base36Lower, model))
+}
+
+fn genesis_controller(block: &Block) -> Option<String> {
+    match block {
+        Block::Cbor(block) => {
+            let controllers = field(field(block, "header")?, "controllers")?;
+            match controllers {
+                Cbor::Array(controllers) => match controllers.first()? {
+                    Cbor::Text(controller) => Some(controller.clone()),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+        Block::Json(value) => value
+            .pointer("/header/controllers/0")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    }
+}
+
+/// Cid a commit links to under `key`
+fn link(block: &Block, key: &str) -> Option<String> {
+    match block {
+        Block::Cbor(block) => match field(block, key)? {
+            Cbor::Tag(42, link) => match link.as_ref() {
+                Cbor::Bytes(bytes) if bytes.first() == Some(&0) => {
+                    Cid::try_from(&bytes[1..]).ok().map(|cid| cid.to_string())
+                }
+                _ => None,
+            },
+            _ => None,
+        },
+        Block::Json(value) => value.get(key).and_then(Value::as_str).map(str::to_string),
+    }
+}
+
+fn field<'a>(block: &'a Cbor, key: &str) -> Option<&'a Cbor> {
+    match block {
+        Cbor::Map(entries) => entries
+            .iter()
+            .find(|(k, _)| matches!(k, Cbor::Text(k) if k == key))
+            .map(|(_, v)| v),
+        _ => None,
+    }
+}
+
+fn parse_cid(cid: &str) -> anyhow::Result<Cid> {
+    Ok(Cid::from_str(cid)?)
+}
+
+fn decode_base64(s: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    let s = s.trim_end_matches('=');
+    if s.contains(['+', '/']) {
+        base64::engine::general_purpose::STANDARD_NO_PAD.decode(s)
+    } else {
+        B64.decode(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(cid: &str, value: Value) -> RawCommit {
+        RawCommit {
+            cid: cid.to_string(),
+            value,
+        }
+    }
+
+    #[test]
+    fn should_detect_broken_chain() {
+        let genesis = "bafyreicaqfrkuz4cjslauwwflb4slionpn6kjpcdeaterkhwx5uqagqlcu";
+        let other = "bafyreigdmqpykrgxyaxtlafqpqhzrb7qy2rh75nldvfd4tucqmnm4ckmly";
+        let stream_id = StreamId {
+            r#type: ceramic_event::StreamIdType::ModelInstanceDocument,
+            cid: Cid::from_str(genesis).unwrap(),
+        };
+        let commits = vec![
+            commit(
+                genesis,
+                serde_json::json!({"header": {"controllers": ["did:key:z6Mk"]}}),
+            ),
+            commit(other, serde_json::json!({"prev": other, "proof": other})),
+            commit(other, serde_json::json!({"data": []})),
+        ];
+        let verification = verify_commits(&stream_id, None, &commits);
+        assert!(!verification.is_valid());
+        assert_eq!(
+            verification.failures,
+            vec![
+                CommitFailure {
+                    cid: other.to_string(),
+                    failure: VerificationFailure::BrokenChain {
+                        expected: genesis.to_string(),
+                        actual: Some(other.to_string()),
+                    },
+                },
+                CommitFailure {
+                    cid: other.to_string(),
+                    failure: VerificationFailure::Unsigned,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn should_hash_unsigned_genesis() {
+        let genesis = "bafyreicaqfrkuz4cjslauwwflb4slionpn6kjpcdeaterkhwx5uqagqlcu";
+        let stream_id = StreamId {
+            r#type: ceramic_event::StreamIdType::ModelInstanceDocument,
+            cid: Cid::from_str(genesis).unwrap(),
+        };
+        let commits = vec![commit(
+            genesis,
+            serde_json::json!({"header": {"controllers": ["did:key:z6Mk"]}}),
+        )];
+        assert!(verify_commits(&stream_id, None, &commits).is_valid());
+
+        let commits = vec![commit(
+            genesis,
+            serde_json::json!({"header": {"controllers": ["did:key:z6Mx"]}}),
+        )];
+        let verification = verify_commits(&stream_id, None, &commits);
+        assert_eq!(
+            verification.failures,
+            vec![CommitFailure {
+                cid: genesis.to_string(),
+                failure: VerificationFailure::InvalidCommit(
+                    "genesis does not hash to the commit cid".to_string()
+                ),
+            }]
+        );
+    }
+
+    #[test]
+    fn should_detect_controller_mismatch() {
+        let genesis = "bafyreicaqfrkuz4cjslauwwflb4slionpn6kjpcdeaterkhwx5uqagqlcu";
+        let stream_id = StreamId {
+            r#type: ceramic_event::StreamIdType::ModelInstanceDocument,
+            cid: Cid::from_str(genesis).unwrap(),
+        };
+        let commits = vec![commit(
+            genesis,
+            serde_json::json!({"header": {"controllers": ["did:key:z6Mk"]}}),
+        )];
+        assert!(verify_commits(&stream_id, Some("did:key:z6Mk"), &commits).is_valid());
+        let verification = verify_commits(&stream_id, Some("did:key:z6Mx"), &commits);
+        assert_eq!(
+            verification.failures,
+            vec![CommitFailure {
+                cid: genesis.to_string(),
+                failure: VerificationFailure::ControllerMismatch {
+                    expected: "did:key:z6Mk".to_string(),
+                    actual: "did:key:z6Mx".to_string(),
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn should_verify_until_tip() {
+        let genesis = "bafyreicaqfrkuz4cjslauwwflb4slionpn6kjpcdeaterkhwx5uqagqlcu";
+        let other = "bafyreigdmqpykrgxyaxtlafqpqhzrb7qy2rh75nldvfd4tucqmnm4ckmly";
+        let stream_id = StreamId {
+            r#type: ceramic_event::StreamIdType::ModelInstanceDocument,
+            cid: Cid::from_str(genesis).unwrap(),
+        };
+        // the unsigned commit after the genesis is outside of the verified log
+        let commits = vec![
+            commit(
+                genesis,
+                serde_json::json!({"header": {"controllers": ["did:key:z6Mk"]}}),
+            ),
+            commit(other, serde_json::json!({"prev": genesis})),
+        ];
+        let tip = Cid::from_str(genesis).unwrap();
+        assert!(verify_commits_until(&stream_id, None, &commits, &tip).is_valid());
+        assert!(!verify_commits(&stream_id, None, &commits).is_valid());
+
+        let tip = Cid::from_str(other).unwrap();
+        let verification = verify_commits_until(&stream_id, None, &commits[..1], &tip);
+        assert_eq!(
+            verification.failures,
+            vec![CommitFailure {
+                cid: other.to_string(),
+                failure: VerificationFailure::InvalidCommit(
+                    "commit is not in the log of the stream".to_string()
+                ),
+            }]
+        );
+    }
+
+    #[test]
+    fn should_parse_rfc3339() {
+        assert_eq!(unix_time("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(unix_time("2024-02-29T12:30:15.250Z"), Some(1709209815));
+        assert_eq!(unix_time("2024-02-29T14:30:15+02:00"), Some(1709209815));
+        assert_eq!(unix_time("2024-02-29"), None);
+    }
+
+    #[cfg(feature = "mock")]
+    mod mock {
+        use crate::api::Pagination;
+        use crate::mock::tests::{setup, Ball};
+        use crate::remote::CeramicRemoteHttpClient;
+        use crate::session::tests::{cacao, signer, DID};
+        use crate::{
+            CommitFailure, DidSession, Error, ModelAccountRelation, ModelDefinition,
+            VerificationFailure,
+        };
+
+        #[tokio::test]
+        async fn should_verify_signed_commits() {
+            let (_mock, ceramic, model) = setup(ModelAccountRelation::List).await;
+            let ceramic = ceramic.with_verification(true);
+            let stream_id = ceramic
+                .create_list_instance(&model, &Ball { radius: 1, red: 2 })
+                .await
+                .unwrap();
+            ceramic
+                .replace(&model, &stream_id, &Ball { radius: 1, red: 3 })
+                .await
+                .unwrap();
+            let verification = ceramic.verify(&stream_id).await.unwrap();
+            assert!(verification.is_valid(), "{:?}", verification.failures);
+            let ball: Ball = ceramic.get_as(&stream_id).await.unwrap();
+            assert_eq!(ball, Ball { radius: 1, red: 3 });
+            let genesis = ceramic
+                .get_at_commit(&crate::CommitId::genesis(stream_id.clone()))
+                .await
+                .unwrap();
+            assert_eq!(genesis.state.unwrap().log.len(), 1);
+            let resp = ceramic
+                .query(&model, None, Pagination::default())
+                .await
+                .unwrap();
+            assert_eq!(resp.edges.len(), 1);
+
+            let model =
+                ModelDefinition::new::<Ball>("OtherBall", ModelAccountRelation::Single).unwrap();
+            let model = ceramic.create_model(&model).await.unwrap();
+            let resp = ceramic
+                .get_or_create_single_instance(&model, &Ball { radius: 1, red: 2 })
+                .await
+                .unwrap();
+            let verification = ceramic.verify(&resp.stream_id).await.unwrap();
+            assert!(verification.is_valid(), "{:?}", verification.failures);
+        }
+
+        #[tokio::test]
+        async fn should_verify_session_commits() {
+            let (mock, _, model) = setup(ModelAccountRelation::List).await;
+
+            let session = DidSession::new(signer().await, cacao(DID, "2100-01-01T00:00:00.000Z"));
+            let ceramic = CeramicRemoteHttpClient::new_with_session(session, mock.url());
+            let stream_id = ceramic
+                .create_list_instance(&model, &Ball { radius: 1, red: 2 })
+                .await
+                .unwrap();
+            ceramic
+                .replace(&model, &stream_id, &Ball { radius: 1, red: 3 })
+                .await
+                .unwrap();
+            // the session key signatures and capabilities are checked, but not the signatures of
+            // the capabilities, so the commits are never reported as verified
+            let verification = ceramic.verify(&stream_id).await.unwrap();
+            let failures: Vec<_> = verification.failures.iter().map(|f| &f.failure).collect();
+            assert!(matches!(
+                failures.as_slice(),
+                [
+                    VerificationFailure::UnsupportedCapability(_),
+                    VerificationFailure::UnsupportedCapability(_)
+                ]
+            ));
+            let verifying = ceramic.clone().with_verification(true);
+            assert!(matches!(
+                verifying.get(&stream_id).await,
+                Err(Error::Verification(_))
+            ));
+            assert!(matches!(
+                verifying
+                    .get_at_commit(&crate::CommitId::genesis(stream_id.clone()))
+                    .await,
+                Err(Error::Verification(_))
+            ));
+
+            let session = DidSession::new(signer().await, cacao(DID, "2020-01-01T00:00:00.000Z"));
+            let ceramic = CeramicRemoteHttpClient::new_with_session(session, mock.url());
+            let stream_id = ceramic
+                .create_list_instance(&model, &Ball { radius: 1, red: 2 })
+                .await
+                .unwrap();
+            let verification = ceramic.verify(&stream_id).await.unwrap();
+            assert!(matches!(
+                verification.failures.as_slice(),
+                [CommitFailure {
+                    failure: VerificationFailure::InvalidCapability(_),
+                    ..
+                }]
+            ));
+        }
+    }
+}